@@ -4,15 +4,8 @@ fn main() {
     println!("=== Social NPC Example ===\n");
 
     // Create NPCs
-    let alice = Npc::builder("Alice")
-        .location("tavern")
-        .activity("drinking ale")
-        .build();
-
-    let bob = Npc::builder("Bob")
-        .location("tavern")
-        .activity("playing cards")
-        .build();
+    let alice = Npc::new("Alice", "tavern", "drinking ale");
+    let bob = Npc::new("Bob", "tavern", "playing cards");
 
     println!("NPCs created:");
     println!("- {} is {} at the {}", alice.name, alice.activity, alice.location);
@@ -66,29 +59,25 @@ fn main() {
     println!();
 
     // Create intents based on memories
-    let intent1 = Intent::with_target(
-        &alice.name,
-        "join_game",
-        &bob.name,
-        "Wants to join Bob's card game for old time's sake",
-    );
+    let intent1 = Intent {
+        npc: alice.name.clone(),
+        thought: "Bob always plays cards here. It would be fun to join him for old time's sake.".to_string(),
+        action: format!("I want to pull up a chair and join {}'s card game", bob.name),
+        dialogue: Some("Deal me in, Bob!".to_string()),
+    };
 
-    let intent2 = Intent::new(
-        &bob.name,
-        "order_round",
-        "Feeling generous after winning at cards",
-    );
+    let intent2 = Intent {
+        npc: bob.name.clone(),
+        thought: "I'm on a winning streak, I feel generous tonight.".to_string(),
+        action: "I want to order a round of drinks for the table".to_string(),
+        dialogue: None,
+    };
 
     println!("Intents formed:");
-    println!("- {}: {} {} because: {}", 
-        intent1.npc, 
-        intent1.action,
-        intent1.target.as_ref().unwrap_or(&"".to_string()),
-        intent1.reason
-    );
-    println!("- {}: {} because: {}", 
-        intent2.npc, 
-        intent2.action,
-        intent2.reason
-    );
-}
\ No newline at end of file
+    for intent in [&intent1, &intent2] {
+        println!("- {}: {} (thinking: {})", intent.npc, intent.action, intent.thought);
+        if let Some(dialogue) = &intent.dialogue {
+            println!("  says: \"{}\"", dialogue);
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Minutes in a day
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+/// In-game time, advanced by one step every time `NpcEngine::execute_turn` runs
+///
+/// ```
+/// use social_npc::clock::{TimeOfDay, WorldClock};
+///
+/// let mut clock = WorldClock::new(1, 23, 30).with_minutes_per_turn(60);
+/// clock.advance();
+/// assert_eq!(clock.to_string(), "day 2, 00:30");
+/// assert_eq!(clock.time_of_day(), TimeOfDay::Night);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorldClock {
+    /// Day of the game, counting from 1
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    /// In-game minutes that pass each turn
+    pub minutes_per_turn: u32,
+}
+
+impl WorldClock {
+    /// A clock at the given time, with minutes and hours past their range carried over
+    pub fn new(day: u32, hour: u32, minute: u32) -> Self {
+        let mut clock = Self {
+            day: day.max(1),
+            hour: 0,
+            minute: 0,
+            minutes_per_turn: 30,
+        };
+        clock.advance_by(hour as u64 * 60 + minute as u64);
+        clock
+    }
+
+    pub fn with_minutes_per_turn(mut self, minutes: u32) -> Self {
+        self.minutes_per_turn = minutes;
+        self
+    }
+
+    /// Move forward by one turn
+    pub fn advance(&mut self) {
+        self.advance_minutes(self.minutes_per_turn);
+    }
+
+    /// Move forward by a number of turns
+    pub fn advance_turns(&mut self, turns: u64) {
+        self.advance_by(turns.saturating_mul(self.minutes_per_turn as u64));
+    }
+
+    pub fn advance_minutes(&mut self, minutes: u32) {
+        self.advance_by(minutes as u64);
+    }
+
+    /// Counts in u64 so no hour, minute or advance can overflow; the day stops at `u32::MAX`
+    fn advance_by(&mut self, minutes: u64) {
+        let total = (self.hour as u64 * 60 + self.minute as u64).saturating_add(minutes);
+        let days = total / MINUTES_PER_DAY as u64;
+        self.day = self.day.saturating_add(days.min(u32::MAX as u64) as u32);
+        self.hour = (total % MINUTES_PER_DAY as u64 / 60) as u32;
+        self.minute = (total % 60) as u32;
+    }
+
+    /// Minutes since midnight
+    pub fn minute_of_day(&self) -> u32 {
+        self.hour * 60 + self.minute
+    }
+
+    pub fn time_of_day(&self) -> TimeOfDay {
+        match self.hour {
+            5..=6 => TimeOfDay::Dawn,
+            7..=11 => TimeOfDay::Morning,
+            12..=16 => TimeOfDay::Afternoon,
+            17..=21 => TimeOfDay::Evening,
+            _ => TimeOfDay::Night,
+        }
+    }
+
+    /// The game time as a timestamp, counting day 1 as 2024-01-01, for stamping memories
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        let epoch = NaiveDate::from_ymd_opt(2024, 1, 1)
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .expect("valid epoch")
+            .and_utc();
+        epoch + Duration::days(self.day as i64 - 1) + Duration::minutes(self.minute_of_day() as i64)
+    }
+
+    /// The time with its part of the day, e.g. "day 3, 06:30 (dawn)"
+    pub fn describe(&self) -> String {
+        format!("{} ({})", self, self.time_of_day())
+    }
+}
+
+/// Day 1 at 08:00, half an hour per turn
+impl Default for WorldClock {
+    fn default() -> Self {
+        Self::new(1, 8, 0)
+    }
+}
+
+impl fmt::Display for WorldClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "day {}, {:02}:{:02}", self.day, self.hour, self.minute)
+    }
+}
+
+/// A rough part of the day, for prompts and time-dependent behaviour
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeOfDay {
+    /// 05:00 to 07:00
+    Dawn,
+    /// 07:00 to 12:00
+    Morning,
+    /// 12:00 to 17:00
+    Afternoon,
+    /// 17:00 to 22:00
+    Evening,
+    /// 22:00 to 05:00
+    Night,
+}
+
+impl fmt::Display for TimeOfDay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            TimeOfDay::Dawn => "dawn",
+            TimeOfDay::Morning => "morning",
+            TimeOfDay::Afternoon => "afternoon",
+            TimeOfDay::Evening => "evening",
+            TimeOfDay::Night => "night",
+        };
+        f.write_str(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_carries_minutes_and_hours_over() {
+        assert_eq!(WorldClock::new(1, 25, 70).to_string(), "day 2, 02:10");
+        assert_eq!(WorldClock::new(0, 0, 0).to_string(), "day 1, 00:00");
+    }
+
+    #[test]
+    fn new_handles_hours_past_u32_minutes() {
+        let clock = WorldClock::new(1, u32::MAX, u32::MAX);
+        let minutes = u32::MAX as u64 * 61;
+        assert_eq!(clock.day as u64, 1 + minutes / 1440);
+        assert_eq!(clock.minute_of_day() as u64, minutes % 1440);
+    }
+
+    #[test]
+    fn advance_rolls_over_midnight() {
+        let mut clock = WorldClock::new(3, 23, 45);
+        clock.advance();
+        assert_eq!(clock.to_string(), "day 4, 00:15");
+    }
+
+    #[test]
+    fn large_advances_carry_into_days() {
+        let mut clock = WorldClock::new(1, 23, 59);
+        clock.advance_minutes(u32::MAX);
+        let minutes = 23 * 60 + 59 + u32::MAX as u64;
+        assert_eq!(clock.day as u64, 1 + minutes / 1440);
+        assert_eq!(clock.minute_of_day() as u64, minutes % 1440);
+
+        let mut clock = WorldClock::new(1, 8, 0).with_minutes_per_turn(90);
+        clock.advance_turns(100);
+        assert_eq!(clock.to_string(), "day 7, 14:00");
+    }
+
+    #[test]
+    fn days_stop_at_the_maximum() {
+        let mut clock = WorldClock::new(u32::MAX, 12, 0).with_minutes_per_turn(u32::MAX);
+        clock.advance_turns(u64::MAX);
+        assert_eq!(clock.day, u32::MAX);
+        assert!(clock.hour < 24 && clock.minute < 60);
+    }
+}
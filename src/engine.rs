@@ -1,15 +1,31 @@
-use anyhow::Result;
-use futures::future::join_all;
+use async_trait::async_trait;
+use futures::{future, stream, StreamExt};
 use serde_json;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
 
-use crate::llm::LlmClient;
-use crate::parser;
+use crate::clock::WorldClock;
+use crate::error::{Error, IoResultExt, Result};
+use crate::events::{EngineEvent, DEFAULT_EVENT_CAPACITY};
+use crate::llm::{LlmClient, LlmRole, QueryContext};
+use crate::parser::JsonFieldStream;
+use crate::partition::{self, Partition};
+use crate::priority::IntentPriority;
 use crate::prompts::PromptBuilder;
-use crate::types::{Contract, GameState, GmInput, GmResponse, Intent, Npc, CurrentState, MemoryUpdateInput};
-use crate::memory::MemoryUpdate;
+use crate::report::{LlmCallStats, NpcError, PhaseDurations, StateDiff, TurnPhase, TurnReport};
+use crate::prompts::templates::PERSONALITY_TEMPLATE;
+use crate::retry::{self, RepairRecord, RetryPolicy};
+use crate::snapshot::{Snapshot, SnapshotInfo, SnapshotStore};
+use crate::transcript::{Transcript, TranscriptStore};
+use crate::validation::{self, Diagnostic, ValidationPolicy};
+use crate::relevance::{RelevanceContext, RelevanceGate, Surroundings};
+use crate::resolver::{GmResolver, LlmResolver, ResolveContext};
+use crate::world::World;
+use crate::types::{Contract, DialogueReply, NpcAction, TranscriptEntry, GameState, GmInput, GmResponse, Intent, Npc, NpcStateFile, CurrentState, MemoryUpdateInput};
+use crate::memory::{MemorySystem, MemoryUpdate};
 
 /// The main NPC engine that manages game state and orchestrates NPC behaviors
 pub struct NpcEngine {
@@ -24,13 +40,82 @@ pub struct NpcEngine {
     
     /// Prompt builder for constructing prompts
     prompt_builder: PromptBuilder,
+    
+    /// Storage for contract transcripts
+    transcripts: TranscriptStore,
+    
+    /// Storage for automatic per-turn snapshots
+    snapshots: SnapshotStore,
+    
+    /// Whether to write a snapshot after every turn
+    auto_snapshot: bool,
+    
+    /// Maximum number of automatic snapshots to keep (all if None)
+    snapshot_retention: Option<usize>,
+    
+    /// How to re-query the LLM when its output can't be parsed
+    retry_policy: RetryPolicy,
+    
+    /// LLM calls that needed repair since the log was last taken
+    repair_log: Mutex<Vec<RepairRecord>>,
+    
+    /// What to do with GM responses that fail validation
+    validation_policy: ValidationPolicy,
+    
+    /// Every LLM call made during the turn in progress, for its report (None between turns)
+    turn_calls: Mutex<Option<Vec<RepairRecord>>>,
+    
+    /// Broadcasts what the engine does to subscribers
+    events: broadcast::Sender<EngineEvent>,
+    
+    /// Intents submitted by players for the next turn, keyed by player name
+    player_intents: Mutex<HashMap<String, Intent>>,
+    
+    /// Whether to stream LLM responses and broadcast dialogue as it is generated
+    streaming: bool,
+    
+    /// Maximum number of intent queries in flight at once (unlimited if None)
+    max_concurrent_intents: Option<usize>,
+    
+    /// Which NPCs are asked for their intent first
+    intent_priority: Option<Arc<dyn IntentPriority>>,
+    
+    /// How long intent collection may take before unfinished NPCs continue what they're doing
+    intent_deadline: Option<Duration>,
+    
+    /// Locations and the routes between them, from `world.json` (anything goes if None)
+    world: Option<Arc<World>>,
+    
+    /// Whether NPCs alone with nothing going on follow their schedule without LLM calls
+    auto_routines: bool,
+    
+    /// Decides which NPCs are worth asking each turn (everyone if None)
+    relevance_gate: Option<Arc<dyn RelevanceGate>>,
+    
+    /// What the relevance gate needs to remember about each NPC between turns
+    attention: Mutex<HashMap<String, Attention>>,
+    
+    /// Whether to resolve each group of NPCs that can affect each other with its own GM call
+    partitioned_gm: bool,
+    
+    /// Decides what happens when NPCs act
+    gm_resolver: Arc<dyn GmResolver>,
+    
+    /// Resolves the turn instead when `gm_resolver` fails
+    gm_fallback: Option<Arc<dyn GmResolver>>,
 }
 
 impl NpcEngine {
     /// Create a new NPC engine with the given data directory and LLM client
     pub fn new(data_path: impl AsRef<Path>, llm_client: impl LlmClient + 'static) -> Result<Self> {
         let data_path = data_path.as_ref().to_path_buf();
-        let prompt_builder = PromptBuilder::new(&data_path);
+        let world = World::load(&data_path)?.map(Arc::new);
+        let mut prompt_builder = PromptBuilder::new(&data_path);
+        if let Some(world) = &world {
+            prompt_builder = prompt_builder.with_world(world.clone());
+        }
+        let transcripts = TranscriptStore::new(&data_path);
+        let snapshots = SnapshotStore::new(&data_path);
         
         // Start with empty state
         let npcs = HashMap::new();
@@ -39,8 +124,29 @@ impl NpcEngine {
         let mut engine = Self {
             data_path,
             llm_client: Arc::new(llm_client),
-            state: Arc::new(Mutex::new(GameState { npcs, contracts })),
+            state: Arc::new(Mutex::new(GameState { npcs, contracts, turn: 0, clock: WorldClock::default() })),
             prompt_builder,
+            transcripts,
+            snapshots,
+            auto_snapshot: false,
+            snapshot_retention: None,
+            retry_policy: RetryPolicy::default(),
+            repair_log: Mutex::new(Vec::new()),
+            validation_policy: ValidationPolicy::default(),
+            turn_calls: Mutex::new(None),
+            events: broadcast::channel(DEFAULT_EVENT_CAPACITY).0,
+            player_intents: Mutex::new(HashMap::new()),
+            streaming: false,
+            max_concurrent_intents: None,
+            intent_priority: None,
+            intent_deadline: None,
+            world,
+            auto_routines: false,
+            relevance_gate: None,
+            attention: Mutex::new(HashMap::new()),
+            partitioned_gm: false,
+            gm_resolver: Arc::new(LlmResolver),
+            gm_fallback: None,
         };
         
         // Load NPCs from data directory
@@ -49,118 +155,499 @@ impl NpcEngine {
         Ok(engine)
     }
     
+    /// Write a snapshot after every executed turn into `data/snapshots`
+    ///
+    /// Unlike `save_snapshot`, automatic snapshots leave each NPC's state.json untouched.
+    pub fn with_auto_snapshots(mut self) -> Self {
+        self.auto_snapshot = true;
+        self
+    }
+    
+    /// Keep only the most recent `keep` automatic snapshots
+    pub fn with_snapshot_retention(mut self, keep: usize) -> Self {
+        self.snapshot_retention = Some(keep);
+        self
+    }
+    
+    /// Set how LLM calls are retried when their output can't be parsed
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+    
+    /// Set what happens to GM responses that reference unknown NPCs, contracts and the like
+    pub fn with_validation_policy(mut self, policy: ValidationPolicy) -> Self {
+        self.validation_policy = policy;
+        self
+    }
+    
+    /// Set how many events each subscriber can fall behind before missing some
+    ///
+    /// Subscribers taken before this call stop receiving events.
+    pub fn with_event_capacity(mut self, capacity: usize) -> Self {
+        self.events = broadcast::channel(capacity.max(1)).0;
+        self
+    }
+    
+    /// Stream LLM responses and emit `EngineEvent::DialogueChunk` events as NPCs speak
+    ///
+    /// Responses are still parsed only once complete, so turns take as long as before;
+    /// subscribers just get to show dialogue while it is being written.
+    pub fn with_streaming(mut self, streaming: bool) -> Self {
+        self.streaming = streaming;
+        self
+    }
+    
+    /// Query at most `max` NPCs for their intent at a time (at least one)
+    ///
+    /// To limit load per backend rather than per engine, wrap clients in `RateLimitedClient`.
+    pub fn with_max_concurrent_intents(mut self, max: usize) -> Self {
+        self.max_concurrent_intents = Some(max.max(1));
+        self
+    }
+    
+    /// Set the order NPCs are queried in, so the ones that matter most are answered first
+    ///
+    /// Without a priority NPCs are queried in name order. Collected intents are always
+    /// returned in name order.
+    pub fn with_intent_priority(mut self, priority: impl IntentPriority + 'static) -> Self {
+        self.intent_priority = Some(Arc::new(priority));
+        self
+    }
+    
+    /// Give intent collection a time limit each turn
+    ///
+    /// NPCs still waiting on the LLM when it runs out are cancelled and continue their
+    /// current activity instead; they are listed in `TurnReport::fallbacks`.
+    pub fn with_intent_deadline(mut self, deadline: Duration) -> Self {
+        self.intent_deadline = Some(deadline);
+        self
+    }
+    
+    /// Use a world map instead of (or without) `world.json` from the data directory
+    ///
+    /// Exits to locations missing from the map are ignored; use `World::validated` to catch them.
+    pub fn with_world(mut self, world: World) -> Self {
+        let world = Arc::new(world);
+        self.prompt_builder = PromptBuilder::new(&self.data_path).with_world(world.clone());
+        self.world = Some(world);
+        self
+    }
+    
+    /// Let NPCs who are alone and not in an interaction follow their schedule without the LLM
+    ///
+    /// Each turn they are moved to wherever their schedule says and take up the scheduled
+    /// activity, skipping their intent, the GM and their memory update. They are listed in
+    /// `TurnReport::routines`, and left out of `collect_intents`. NPCs without a block for
+    /// the current hour are always asked as usual.
+    pub fn with_auto_routines(mut self, enabled: bool) -> Self {
+        self.auto_routines = enabled;
+        self
+    }
+    
+    /// Only ask NPCs for their intent when the gate finds something worth reacting to
+    ///
+    /// The others continue their current activity: their "continues ..." intent is listed in
+    /// `TurnReport::idle` and broadcast as `EngineEvent::NpcIdle`, but isn't sent to the GM
+    /// and doesn't update their memories.
+    pub fn with_relevance_gate(mut self, gate: impl RelevanceGate + 'static) -> Self {
+        self.relevance_gate = Some(Arc::new(gate));
+        self
+    }
+    
+    /// Split GM resolution by location, with one GM call per group of NPCs run in parallel
+    ///
+    /// Each call only sees the acting NPCs, everyone sharing a location, contract or mention
+    /// with them, and the nearby map. The responses are merged into one; NPCs or contracts
+    /// claimed by several calls are reported as `Diagnostic::Conflicting*` and handled by the
+    /// validation policy. If any call fails the whole resolution fails and nothing is applied.
+    /// To limit how many GM calls run at once, wrap the client in `RateLimitedClient`.
+    pub fn with_partitioned_gm(mut self, enabled: bool) -> Self {
+        self.partitioned_gm = enabled;
+        self
+    }
+    
+    /// Resolve intents with something other than the LLM GM, such as `RuleBasedResolver`
+    pub fn with_gm_resolver(mut self, resolver: impl GmResolver + 'static) -> Self {
+        self.gm_resolver = Arc::new(resolver);
+        self
+    }
+    
+    /// Resolve the turn with `fallback` whenever the GM resolver fails
+    ///
+    /// Each use is logged and broadcast as `EngineEvent::GmFallback`.
+    pub fn with_gm_fallback(mut self, fallback: impl GmResolver + 'static) -> Self {
+        self.gm_fallback = Some(Arc::new(fallback));
+        self
+    }
+    
+    /// Set the in-game time and how much of it passes each turn
+    pub fn with_clock(self, clock: WorldClock) -> Self {
+        self.state.lock().unwrap().clock = clock;
+        self
+    }
+    
+    /// The current in-game time
+    pub fn clock(&self) -> WorldClock {
+        self.state.lock().unwrap().clock
+    }
+    
+    /// The world map, if the game has one
+    pub fn world(&self) -> Option<&World> {
+        self.world.as_deref()
+    }
+    
+    /// Subscribe to events from the engine, starting with the next one emitted
+    pub fn subscribe(&self) -> broadcast::Receiver<EngineEvent> {
+        self.events.subscribe()
+    }
+    
+    fn emit(&self, event: EngineEvent) {
+        // Sending only fails when nobody is subscribed, which is fine
+        let _ = self.events.send(event);
+    }
+    
+    /// Take the records of LLM calls that needed repair or failed since the last call
+    pub fn take_repair_log(&self) -> Vec<RepairRecord> {
+        std::mem::take(&mut *self.repair_log.lock().unwrap())
+    }
+    
+    /// Query the LLM and parse the response, retrying with repair prompts per the retry policy
+    async fn query_json<T: serde::de::DeserializeOwned>(
+        &self,
+        prompt: String,
+        context: &QueryContext,
+    ) -> Result<T> {
+        self.emit(EngineEvent::LlmCallStarted { context: context.clone() });
+        let started = Instant::now();
+        
+        let tap = DialogueTap { client: self.llm_client.as_ref(), events: &self.events };
+        let client: &dyn LlmClient = if self.streaming { &tap } else { self.llm_client.as_ref() };
+        
+        let (result, record) = retry::query_json(
+            client,
+            &self.prompt_builder,
+            &self.retry_policy,
+            prompt,
+            context,
+        ).await;
+        
+        self.emit(EngineEvent::LlmCallFinished {
+            context: context.clone(),
+            attempts: record.attempts,
+            succeeded: record.succeeded,
+            duration: started.elapsed(),
+        });
+        
+        if let Some(calls) = self.turn_calls.lock().unwrap().as_mut() {
+            calls.push(record.clone());
+        }
+        
+        if !record.is_clean() {
+            if record.succeeded {
+                log::info!(
+                    "  🔧 {:?} call repaired after {} attempt(s) {:?}",
+                    record.context.role,
+                    record.attempts,
+                    record.json_repairs
+                );
+            }
+            self.repair_log.lock().unwrap().push(record);
+        }
+        
+        result
+    }
+    
     /// Get the current game state
     pub fn get_state(&self) -> GameState {
         self.state.lock().unwrap().clone()
     }
     
     /// Update the game state
-    pub fn update_state<F>(&self, updater: F) -> Result<()> 
+    pub fn update_state<F, T>(&self, updater: F) -> Result<T> 
     where
-        F: FnOnce(&mut GameState) -> Result<()>
+        F: FnOnce(&mut GameState) -> Result<T>
     {
         let mut state = self.state.lock().unwrap();
         updater(&mut state)
     }
     
-    /// Collect intents from all NPCs
+    /// Collect intents from all NPCs, leaving out those the relevance gate lets carry on
     pub async fn collect_intents(&self) -> Result<Vec<Intent>> {
-        let npcs_to_process = self.get_state().npcs;
+        Ok(self.collect_intents_detailed().await?.intents)
+    }
+    
+    /// Collect intents, also returning the NPCs that failed or ran out of time
+    ///
+    /// Fatal errors, such as a replay diverging from its cassette, fail the whole collection.
+    async fn collect_intents_detailed(&self) -> Result<CollectedIntents> {
+        let started = Instant::now();
+        let game_state = self.get_state();
+        let player_intents = self.take_player_intents(&game_state);
+        
+        // Process NPCs in name order so prompts and results are reproducible between runs
+        let mut npcs_to_process: Vec<_> = game_state
+            .npcs
+            .iter()
+            .filter(|(_, npc)| !npc.player_controlled)
+            .map(|(name, npc)| (name.clone(), npc.clone()))
+            .collect();
+        npcs_to_process.sort_by(|a, b| a.0.cmp(&b.0));
+        
+        // NPCs going about their day on their own need no LLM call
+        let mut routines = Vec::new();
+        if self.auto_routines {
+            npcs_to_process.retain(|(name, npc)| {
+                let routine = on_routine(npc, &game_state);
+                if routine {
+                    routines.push(name.clone());
+                }
+                !routine
+            });
+            if !routines.is_empty() {
+                log::debug!("{} NPC(s) follow their routine this turn", routines.len());
+            }
+        }
+        
+        // NPCs with nothing new around them carry on without an LLM call
+        let mut idle = Vec::new();
+        if let Some(gate) = &self.relevance_gate {
+            let mut attention = self.attention.lock().unwrap();
+            npcs_to_process.retain(|(name, npc)| {
+                let entry = attention.get(name);
+                let context = RelevanceContext {
+                    npc,
+                    game_state: &game_state,
+                    last_seen: entry.map(|entry| &entry.last_seen),
+                    idle_turns: entry.map_or(0, |entry| entry.idle_turns),
+                    prompt_pending: entry.is_some_and(|entry| entry.prompt_pending),
+                };
+                match gate.wake_reason(&context) {
+                    Some(reason) => {
+                        log::debug!("{} is asked this turn: {:?}", name, reason);
+                        attention.insert(name.clone(), Attention {
+                            last_seen: Surroundings::observe(npc, &game_state),
+                            idle_turns: 0,
+                            prompt_pending: false,
+                        });
+                        true
+                    }
+                    None => {
+                        if let Some(entry) = attention.get_mut(name) {
+                            entry.idle_turns += 1;
+                        }
+                        idle.push(continue_intent(npc));
+                        false
+                    }
+                }
+            });
+            drop(attention);
+            
+            if !idle.is_empty() {
+                log::info!("{} NPC(s) continue their current activity without being asked", idle.len());
+            }
+            for intent in &idle {
+                self.emit(EngineEvent::NpcIdle { intent: intent.clone() });
+            }
+        }
         
-        if npcs_to_process.is_empty() {
+        if npcs_to_process.is_empty() && player_intents.is_empty() {
             log::debug!("No NPCs to collect intents from");
-            return Ok(Vec::new());
+            return Ok(CollectedIntents { routines, idle, ..Default::default() });
+        }
+        
+        // Highest priority first; the sort is stable so ties stay in name order
+        if let Some(priority) = &self.intent_priority {
+            npcs_to_process.sort_by_cached_key(|(_, npc)| std::cmp::Reverse(priority.priority(npc, &game_state)));
         }
         
         let total_npcs = npcs_to_process.len();
-        log::debug!("Collecting intents from {} NPCs in parallel", total_npcs);
+        let concurrency = self.max_concurrent_intents.unwrap_or(total_npcs).max(1);
+        log::debug!("Collecting intents from {} NPCs, {} at a time", total_npcs, concurrency);
         
-        let game_state = self.get_state();
+        let mut pending: BTreeMap<String, Npc> = npcs_to_process.iter().cloned().collect();
+        let mut results = stream::iter(npcs_to_process)
+            .map(|(name, npc)| self.collect_single_intent(name, npc, &game_state))
+            .buffer_unordered(concurrency);
         
-        // Create futures for all NPCs
-        let intent_futures: Vec<_> = npcs_to_process
-            .iter()
-            .map(|(name, npc)| {
-                let name = name.clone();
-                let npc = npc.clone();
-                let game_state = game_state.clone();
-                let llm_client = Arc::clone(&self.llm_client);
-                let prompt_builder = &self.prompt_builder;
-                
-                async move {
-                    Self::collect_single_intent(
-                        name,
-                        npc,
-                        game_state,
-                        llm_client,
-                        prompt_builder,
-                    ).await
-                }
+        let mut collected = CollectedIntents { routines, idle, ..Default::default() };
+        let deadline = self.intent_deadline.map(|deadline| tokio::time::Instant::from_std(started + deadline));
+        loop {
+            let next = match deadline {
+                Some(deadline) => match tokio::time::timeout_at(deadline, results.next()).await {
+                    Ok(next) => next,
+                    Err(_) => break,
+                },
+                None => results.next().await,
+            };
+            let Some((name, result)) = next else { break };
+            
+            pending.remove(&name);
+            match result {
+                Ok(intent) => collected.intents.push(intent),
+                Err(e) if e.is_fatal() => return Err(e),
+                Err(e) => collected.errors.push(NpcError {
+                    npc: name,
+                    phase: TurnPhase::Intents,
+                    error: e.to_string(),
+                }),
+            }
+        }
+        // Cancels the queries still in flight
+        drop(results);
+        
+        log::info!("Collected {} intents from {} NPCs", collected.intents.len(), total_npcs);
+        
+        // Whoever didn't answer in time carries on as they were
+        if !pending.is_empty() {
+            log::warn!("Intent deadline passed, {} NPC(s) continue their current activity", pending.len());
+        }
+        for (name, npc) in pending {
+            let intent = continue_intent(&npc);
+            log::info!("  ⏱️ {}: {}", name, intent.action);
+            self.emit(EngineEvent::IntentTimedOut { intent: intent.clone() });
+            collected.intents.push(intent);
+            collected.fallbacks.push(name);
+        }
+        
+        // Players act alongside NPCs, keeping everything in name order
+        if !player_intents.is_empty() {
+            log::info!("Adding {} player intent(s)", player_intents.len());
+            collected.intents.extend(player_intents);
+        }
+        collected.intents.sort_by(|a, b| a.npc.cmp(&b.npc));
+        collected.errors.sort_by(|a, b| a.npc.cmp(&b.npc));
+        
+        Ok(collected)
+    }
+    
+    /// Take the queued player intents for players still in the game, in name order
+    fn take_player_intents(&self, game_state: &GameState) -> Vec<Intent> {
+        let pending = std::mem::take(&mut *self.player_intents.lock().unwrap());
+        
+        let mut intents: Vec<Intent> = pending
+            .into_values()
+            .filter(|intent| {
+                game_state.npcs.get(&intent.npc).is_some_and(|npc| npc.player_controlled)
             })
             .collect();
+        intents.sort_by(|a, b| a.npc.cmp(&b.npc));
+        
+        for intent in &intents {
+            log::info!("  🎮 {}: {}", intent.npc, intent.action);
+            self.emit(EngineEvent::IntentCollected { intent: intent.clone() });
+        }
         
-        // Wait for all intents to be collected in parallel
-        let results = join_all(intent_futures).await;
+        intents
+    }
+    
+    /// Add a player-controlled character to the game
+    ///
+    /// Players are part of the game state and are resolved by the GM like NPCs, but their
+    /// intents come from `submit_player_intent` instead of the LLM and they keep no memories.
+    pub fn register_player(&self, name: &str, location: impl Into<String>, activity: impl Into<String>) -> Result<()> {
+        if name.trim().is_empty() {
+            return Err(Error::invalid("Player name must not be empty"));
+        }
         
-        // Filter out None values and collect successful intents
-        let intents: Vec<Intent> = results.into_iter().flatten().collect();
+        let mut player = Npc::new(name, location, activity);
+        player.folder_path = String::new();
+        player.player_controlled = true;
         
-        log::info!("Collected {} intents from {} NPCs", intents.len(), total_npcs);
+        self.update_state(|state| {
+            if state.npcs.contains_key(name) {
+                return Err(Error::invalid(format!("A character named '{}' already exists", name)));
+            }
+            log::info!("Registered player: {}", name);
+            state.npcs.insert(name.to_string(), player);
+            Ok(())
+        })
+    }
+    
+    /// Queue what a player does in the next turn, replacing anything already queued for them
+    ///
+    /// `intent.npc` names the player. A player with nothing queued sits the turn out.
+    pub fn submit_player_intent(&self, intent: Intent) -> Result<()> {
+        match self.get_state().npcs.get(&intent.npc) {
+            None => return Err(Error::NpcNotFound { name: intent.npc }),
+            Some(npc) if !npc.player_controlled => {
+                return Err(Error::invalid(format!("'{}' is not player-controlled", intent.npc)));
+            }
+            Some(_) => {}
+        }
         
-        Ok(intents)
+        self.player_intents.lock().unwrap().insert(intent.npc.clone(), intent);
+        Ok(())
+    }
+    
+    /// The GM's prompt to a player describing the outcome of their last action
+    pub fn player_prompt(&self, name: &str) -> Option<String> {
+        self.get_state()
+            .npcs
+            .get(name)
+            .filter(|npc| npc.player_controlled)
+            .and_then(|npc| npc.next_prompt.clone())
     }
     
     async fn collect_single_intent(
+        &self,
         name: String,
         npc: Npc,
-        game_state: GameState,
-        llm_client: Arc<dyn LlmClient>,
-        prompt_builder: &PromptBuilder,
-    ) -> Option<Intent> {
+        game_state: &GameState,
+    ) -> (String, Result<Intent>) {
         log::debug!("Getting intent from {}", name);
         
         // Build prompt
-        let prompt = match prompt_builder.build_npc_intent_prompt(&npc, &game_state) {
+        let prompt = match self.prompt_builder.build_npc_intent_prompt(&npc, game_state) {
             Ok(p) => p,
             Err(e) => {
                 log::error!("Failed to build prompt for {}: {}", name, e);
-                return None;
+                self.emit(EngineEvent::IntentFailed { npc: name.clone(), error: e.to_string() });
+                return (name, Err(e));
             }
         };
         
-        // Query LLM
+        // Query LLM and parse response
         log::info!("🎭 Collecting intent from {}", name);
-        let response = match llm_client.query(prompt, Path::new(".")).await {
-            Ok(resp) => resp,
-            Err(e) => {
-                log::error!("Failed to get response from LLM for {}: {}", name, e);
-                return None;
-            }
-        };
-        
-        // Parse response
-        match parser::extract_json::<Intent>(&response) {
-            Ok(intent) => {
+        match self.query_json::<Intent>(prompt, &QueryContext::intent(&name)).await {
+            Ok(mut intent) => {
+                // Attribute the intent to the NPC we asked, whatever name the model wrote
+                intent.npc = name.clone();
                 log::info!("  💭 {}: {}", name, intent.action);
-                Some(intent)
+                self.emit(EngineEvent::IntentCollected { intent: intent.clone() });
+                (name, Ok(intent))
             }
             Err(e) => {
-                log::error!("Failed to parse intent from {}: {}", name, e);
-                None
+                log::error!("Failed to get intent from {}: {}", name, e);
+                self.emit(EngineEvent::IntentFailed { npc: name.clone(), error: e.to_string() });
+                (name, Err(e))
             }
         }
     }
     
-    /// Have the GM resolve intents into reality
+    /// Have the GM resolve intents into reality, through the configured `GmResolver`
     pub async fn resolve_intents(&self, intents: Vec<Intent>) -> Result<GmResponse> {
+        let (gm_response, _) = self.resolve_intents_detailed(intents).await?;
+        Ok(gm_response)
+    }
+    
+    /// Resolve intents, also returning the problems validation found in the GM response
+    async fn resolve_intents_detailed(&self, intents: Vec<Intent>) -> Result<(GmResponse, Vec<Diagnostic>)> {
         if intents.is_empty() {
             log::debug!("No intents to resolve");
-            return Ok(GmResponse {
+            // Nobody acted, but travellers still get further along
+            let events = self.update_state(|state| Ok(advance_travellers(state)))?;
+            for event in events {
+                self.emit(event);
+            }
+            let nothing = GmResponse {
                 reality: "Nothing happened.".to_string(),
                 state_changes: Vec::new(),
                 contracts: Vec::new(),
                 next_prompts: HashMap::new(),
-            });
+                local_realities: HashMap::new(),
+            };
+            return Ok((nothing, Vec::new()));
         }
         
         log::info!("🎲 Resolving {} intents with GM", intents.len());
@@ -168,37 +655,47 @@ impl NpcEngine {
         // Get current game state
         let game_state = self.get_state();
         
-        // Prepare input for GM
-        let gm_input = GmInput {
-            current_state: CurrentState {
-                npcs: game_state.npcs.clone(),
-                active_contracts: game_state.contracts.clone(),
-            },
-            intents,
+        // Query LLM and parse response, in parallel per partition if enabled
+        let (mut gm_response, mut diagnostics) = if self.partitioned_gm {
+            let partitions = partition::partition_intents(&intents, &game_state, self.world.as_deref());
+            log::info!("Resolving in {} GM partition(s)", partitions.len());
+            let inputs: Vec<GmInput> = partitions
+                .iter()
+                .map(|partition| self.gm_input(&game_state, partition.intents.clone(), Some(partition)))
+                .collect();
+            let responses = future::try_join_all(inputs.iter().map(|input| self.query_gm(input))).await?;
+            partition::merge_responses(&partitions, responses)
+        } else {
+            let gm_input = self.gm_input(&game_state, intents.clone(), None);
+            (self.query_gm(&gm_input).await?, Vec::new())
         };
-        
-        let input_json = serde_json::to_string_pretty(&gm_input)?;
-        log::debug!("Sending to GM: {}", input_json);
-        
-        // Build GM prompt
-        let prompt = self.prompt_builder.build_gm_prompt(&input_json)?;
-        
-        // Query LLM
-        let response = self.llm_client
-            .query(prompt, Path::new("."))
-            .await?;
-        
-        // Parse response
-        let gm_response: GmResponse = parser::extract_json(&response)?;
         log::info!("🎭 Reality: {}", gm_response.reality);
         
-        // Apply state changes
-        self.update_state(|state| {
+        // Check the response makes sense for the current state before applying it
+        diagnostics.extend(match self.validation_policy {
+            ValidationPolicy::AutoFix => validation::auto_fix(&mut gm_response, &game_state, &intents),
+            _ => validation::validate_gm_response(&gm_response, &game_state, &intents),
+        });
+        // Nobody goes anywhere off the map, whatever the policy
+        if let Some(world) = &self.world {
+            diagnostics.extend(validation::fix_locations(&mut gm_response, &game_state, world));
+        }
+        if !diagnostics.is_empty() && self.validation_policy == ValidationPolicy::Reject {
+            return Err(Error::Validation(diagnostics));
+        }
+        for diagnostic in &diagnostics {
+            log::warn!("GM response: {}", diagnostic);
+        }
+        
+        // Apply state changes, collecting events to send once the state is unlocked
+        let applied_events = self.update_state(|state| {
+            // Journeys already under way cover another turn before anyone sets off
+            let mut events = advance_travellers(state);
+            
             for change in &gm_response.state_changes {
                 if let Some(npc) = state.npcs.get_mut(&change.npc) {
-                    npc.location = change.location.clone();
-                    npc.activity = change.activity.clone();
-                    log::info!("  📍 {}: {} - {}", change.npc, change.location, change.activity);
+                    events.extend(self.change_npc_state(npc, &change.location, &change.activity));
+                    log::info!("  📍 {}: {} - {}", change.npc, npc.location, change.activity);
                 }
             }
             
@@ -209,7 +706,10 @@ impl NpcEngine {
                         let contract = Contract {
                             id: contract_update.id.clone(),
                             participants: contract_update.participants.clone(),
-                            transcript_file: format!("data/contracts/{}.json", contract_update.id),
+                            transcript_file: self.transcripts
+                                .path_for(&contract_update.id)
+                                .to_string_lossy()
+                                .to_string(),
                         };
                         
                         // Update NPCs' active_contract field
@@ -222,21 +722,32 @@ impl NpcEngine {
                         // Add to game state
                         state.contracts.insert(contract.id.clone(), contract);
                         log::info!("  📜 Contract created: {}", contract_update.id);
+                        events.push(EngineEvent::ContractCreated {
+                            id: contract_update.id.clone(),
+                            participants: contract_update.participants.clone(),
+                        });
                     }
                     "update" => {
-                        // Contract continues, append to transcript if needed
+                        // Contract continues, transcript entry is appended below
                         log::info!("  📜 Contract updated: {}", contract_update.id);
+                        events.push(EngineEvent::ContractUpdated { id: contract_update.id.clone() });
                     }
                     "end" => {
                         // Remove contract and clear NPCs' active_contract
+                        let mut participants = Vec::new();
                         if let Some(contract) = state.contracts.remove(&contract_update.id) {
                             for participant in &contract.participants {
                                 if let Some(npc) = state.npcs.get_mut(participant) {
                                     npc.active_contract = None;
                                 }
                             }
+                            participants = contract.participants;
                         }
                         log::info!("  📜 Contract ended: {}", contract_update.id);
+                        events.push(EngineEvent::ContractEnded {
+                            id: contract_update.id.clone(),
+                            participants,
+                        });
                     }
                     _ => log::warn!("Unknown contract action: {}", contract_update.action),
                 }
@@ -249,17 +760,425 @@ impl NpcEngine {
                 }
             }
             
-            Ok(())
+            Ok(events)
         })?;
         
-        Ok(gm_response)
+        self.emit(EngineEvent::GmResolved {
+            response: gm_response.clone(),
+            diagnostics: diagnostics.clone(),
+        });
+        for event in applied_events {
+            self.emit(event);
+        }
+        
+        // Persist transcript entries so the next turn's prompts can see the conversation
+        for contract_update in &gm_response.contracts {
+            if !matches!(contract_update.action.as_str(), "create" | "update" | "end") {
+                continue;
+            }
+            
+            if let Some(entry) = &contract_update.transcript_entry {
+                if let Err(e) = self.transcripts.append(
+                    &contract_update.id,
+                    &contract_update.participants,
+                    &contract_update.action,
+                    entry.clone(),
+                ) {
+                    log::error!("Failed to append transcript for {}: {}", contract_update.id, e);
+                }
+            }
+        }
+        
+        // The GM speaking to NPCs who didn't act wakes them next turn
+        if self.relevance_gate.is_some() {
+            let game_state = self.get_state();
+            let mut attention = self.attention.lock().unwrap();
+            for name in gm_response.next_prompts.keys() {
+                if intents.iter().any(|intent| &intent.npc == name) {
+                    continue;
+                }
+                if let Some(entry) = attention.get_mut(name) {
+                    entry.prompt_pending = true;
+                }
+            }
+            
+            // NPCs who acted saw where their own action took them, so it doesn't wake them next turn
+            for intent in &intents {
+                let (Some(npc), Some(entry)) = (game_state.npcs.get(&intent.npc), attention.get_mut(&intent.npc)) else {
+                    continue;
+                };
+                let now = Surroundings::observe(npc, &game_state);
+                if now.location != entry.last_seen.location {
+                    entry.last_seen.company = now.company;
+                }
+                entry.last_seen.location = now.location;
+                entry.last_seen.activity = now.activity;
+            }
+        }
+        
+        Ok((gm_response, diagnostics))
+    }
+    
+    /// What the GM is shown: the whole world, or just the NPCs, contracts and map around a partition
+    fn gm_input(&self, game_state: &GameState, intents: Vec<Intent>, partition: Option<&Partition>) -> GmInput {
+        let mut npcs = game_state.npcs.clone();
+        let mut active_contracts = game_state.contracts.clone();
+        let mut locations = self.world.as_ref().map(|world| world.locations.clone());
+        
+        if let Some(partition) = partition {
+            npcs.retain(|name, _| partition.npcs.contains(name));
+            active_contracts.retain(|_, contract| contract.participants.iter().any(|name| partition.npcs.contains(name)));
+            // The partition's locations and their neighbours, so the GM can still send people off
+            if let Some(locations) = &mut locations {
+                let nearby: HashSet<String> = partition
+                    .locations
+                    .iter()
+                    .filter_map(|name| locations.get(name))
+                    .flat_map(|location| location.exits.keys().cloned())
+                    .chain(partition.locations.iter().cloned())
+                    .collect();
+                locations.retain(|name, _| nearby.contains(name));
+            }
+        }
+        
+        GmInput {
+            current_state: CurrentState {
+                time: game_state.clock.describe(),
+                minutes_per_turn: game_state.clock.minutes_per_turn,
+                npcs,
+                active_contracts,
+            },
+            intents,
+            locations,
+        }
+    }
+    
+    /// Resolve one GM input with the resolver, falling back if it fails and a fallback is set
+    async fn query_gm(&self, gm_input: &GmInput) -> Result<GmResponse> {
+        let context = ResolveContext { engine: self };
+        let error = match self.gm_resolver.resolve(gm_input, &context).await {
+            Ok(response) => return Ok(response),
+            Err(e) => e,
+        };
+        let Some(fallback) = self.gm_fallback.as_ref().filter(|_| !error.is_fatal()) else {
+            return Err(error);
+        };
+        
+        log::warn!("GM resolution failed, using the fallback resolver: {}", error);
+        self.emit(EngineEvent::GmFallback { error: error.to_string() });
+        fallback.resolve(gm_input, &context).await
+    }
+    
+    /// Send one GM query to the LLM and parse its response
+    pub(crate) async fn query_gm_llm(&self, gm_input: &GmInput) -> Result<GmResponse> {
+        // Going through Value sorts map keys, keeping the prompt identical for identical state
+        let input_json = serde_json::to_string_pretty(&serde_json::to_value(gm_input)?)?;
+        log::debug!("Sending to GM: {}", input_json);
+        
+        let prompt = self.prompt_builder.build_gm_prompt(&input_json)?;
+        self.query_json(prompt, &QueryContext::gm()).await
+    }
+    
+    /// Put NPCs left to their routine wherever their schedule says, unless the GM moved them itself
+    fn follow_routines(&self, names: &[String], reality: &GmResponse) -> Result<()> {
+        let events = self.update_state(|state| {
+            let clock = state.clock;
+            let mut events = Vec::new();
+            
+            for name in names {
+                if reality.state_changes.iter().any(|change| &change.npc == name) {
+                    continue;
+                }
+                let Some(npc) = state.npcs.get_mut(name) else {
+                    continue;
+                };
+                let Some(entry) = npc.scheduled(&clock).cloned() else {
+                    continue;
+                };
+                
+                let changes = self.change_npc_state(npc, &entry.location, &entry.activity);
+                if !changes.is_empty() {
+                    log::info!("  🔁 {}: {} - {}", name, npc.location, npc.activity);
+                }
+                events.extend(changes);
+            }
+            
+            Ok(events)
+        })?;
+        
+        for event in events {
+            self.emit(event);
+        }
+        Ok(())
+    }
+    
+    /// Move an NPC and set their activity, returning the events describing what changed
+    fn change_npc_state(&self, npc: &mut Npc, location: &str, activity: &str) -> Vec<EngineEvent> {
+        let mut events = Vec::new();
+        
+        let from = npc.location.clone();
+        let was_heading_to = npc.travel.as_ref().map(|t| t.destination.clone());
+        self.move_npc(npc, location);
+        if npc.location != from {
+            events.push(EngineEvent::NpcMoved {
+                npc: npc.name.clone(),
+                from,
+                to: npc.location.clone(),
+            });
+        }
+        if let Some(travel) = &npc.travel {
+            if was_heading_to.as_ref() != Some(&travel.destination) {
+                events.push(EngineEvent::TravelStarted {
+                    npc: npc.name.clone(),
+                    destination: travel.destination.clone(),
+                    turns_left: travel.turns_left(),
+                });
+            }
+        }
+        if npc.activity != activity {
+            events.push(EngineEvent::NpcActivityChanged {
+                npc: npc.name.clone(),
+                from: npc.activity.clone(),
+                to: activity.to_string(),
+            });
+        }
+        npc.activity = activity.to_string();
+        
+        events
+    }
+    
+    /// Send an NPC to a location, setting them travelling if the world map says it takes a while
+    ///
+    /// With a world map, NPCs asked to go somewhere unknown or unreachable stay where they are.
+    fn move_npc(&self, npc: &mut Npc, to: &str) {
+        let Some(world) = &self.world else {
+            npc.location = to.to_string();
+            return;
+        };
+        
+        // Already there, or already on the way
+        if npc.location == to || npc.travel.as_ref().is_some_and(|t| t.destination == to) {
+            return;
+        }
+        if !world.contains(to) {
+            log::warn!("{} stays at '{}': '{}' isn't on the world map", npc.name, npc.location, to);
+            return;
+        }
+        if !world.contains(&npc.location) {
+            // Stepping onto the map from somewhere off it
+            npc.location = to.to_string();
+            npc.travel = None;
+            return;
+        }
+        if !world.start_travel(npc, to) {
+            log::warn!("{} stays at '{}': there is no way to '{}'", npc.name, npc.location, to);
+        }
+    }
+    
+    /// Get the transcript recorded so far for a contract
+    pub fn contract_transcript(&self, contract_id: &str) -> Result<Transcript> {
+        self.transcripts.load(contract_id)
+    }
+    
+    /// Have an NPC answer something said to them directly, outside the turn loop
+    ///
+    /// The exchange is recorded in the transcript of the NPC's current interaction, which
+    /// the speaker joins, or of a new interaction between the two. The NPC's memories are
+    /// updated afterwards; a failed memory update is logged but doesn't fail the call.
+    ///
+    /// The speaker must be another character in the game, an NPC or a registered player.
+    pub async fn converse(&self, npc_name: &str, speaker: &str, utterance: &str) -> Result<DialogueReply> {
+        let game_state = self.get_state();
+        let npc = match game_state.npcs.get(npc_name) {
+            Some(npc) if npc.player_controlled => {
+                return Err(Error::invalid(format!("'{}' is player-controlled", npc_name)));
+            }
+            Some(npc) => npc,
+            None => return Err(Error::NpcNotFound { name: npc_name.to_string() }),
+        };
+        check_speaker(&game_state, npc_name, speaker)?;
+        
+        log::info!("🗣️ {} to {}: {}", speaker, npc_name, utterance);
+        let prompt = self.prompt_builder.build_dialogue_prompt(npc, &game_state, speaker, utterance)?;
+        let reply: DialogueReply = self.query_json(prompt, &QueryContext::dialogue(npc_name)).await?;
+        log::info!("  💬 {}: {}", npc_name, reply.dialogue.as_deref().unwrap_or("(silent)"));
+        
+        // Only a conversation that actually happened ties the two together
+        let (contract_id, created, events) = self.join_or_start_conversation(npc_name, speaker)?;
+        for event in events {
+            self.emit(event);
+        }
+        let participants = self.get_state().contracts.get(&contract_id)
+            .map(|c| c.participants.clone())
+            .unwrap_or_default();
+        if created {
+            self.emit(EngineEvent::ContractCreated {
+                id: contract_id.clone(),
+                participants: participants.clone(),
+            });
+        }
+        
+        // The utterance goes in the reality so it reads before the reply
+        let mut details = HashMap::new();
+        details.insert(npc_name.to_string(), NpcAction {
+            action: reply.action.clone(),
+            dialogue: reply.dialogue.clone(),
+        });
+        let entry = TranscriptEntry {
+            reality: format!("{} said to {}: \"{}\"", speaker, npc_name, utterance),
+            details,
+        };
+        let action = if created { "create" } else { "update" };
+        if let Err(e) = self.transcripts.append(&contract_id, &participants, action, entry) {
+            log::error!("Failed to append transcript for {}: {}", contract_id, e);
+        }
+        
+        self.emit(EngineEvent::NpcReplied {
+            npc: npc_name.to_string(),
+            speaker: speaker.to_string(),
+            utterance: utterance.to_string(),
+            reply: reply.clone(),
+        });
+        
+        let reality = match &reply.dialogue {
+            Some(dialogue) => format!(
+                "{} said to {}: \"{}\". {} replied: \"{}\"",
+                speaker, npc_name, utterance, npc_name, dialogue
+            ),
+            None => format!(
+                "{} said to {}: \"{}\". {} said nothing.",
+                speaker, npc_name, utterance, npc_name
+            ),
+        };
+        let input = MemoryUpdateInput {
+            npc_name: npc_name.to_string(),
+            intent: Intent {
+                npc: npc_name.to_string(),
+                thought: reply.thought.clone(),
+                action: reply.action.clone(),
+                dialogue: reply.dialogue.clone(),
+            },
+            reality,
+            other_npcs_present: vec![speaker.to_string()],
+        };
+        if let Err(e) = self.update_single_npc_memory(input).await {
+            if e.is_fatal() {
+                return Err(e);
+            }
+            log::error!("Failed to update memory for {}: {}", npc_name, e);
+            self.emit(EngineEvent::MemoryUpdateFailed { npc: npc_name.to_string(), error: e.to_string() });
+        }
+        
+        Ok(reply)
+    }
+    
+    /// End an interaction, such as a conversation started by `converse`, freeing its participants
+    pub fn end_conversation(&self, contract_id: &str) -> Result<()> {
+        let contract = self.update_state(|state| {
+            let contract = state.contracts.remove(contract_id)
+                .ok_or_else(|| Error::invalid(format!("no interaction '{}'", contract_id)))?;
+            for participant in &contract.participants {
+                if let Some(npc) = state.npcs.get_mut(participant) {
+                    if npc.active_contract.as_deref() == Some(contract_id) {
+                        npc.active_contract = None;
+                    }
+                }
+            }
+            Ok(contract)
+        })?;
+        
+        log::info!("  📜 Contract ended: {}", contract_id);
+        self.emit(EngineEvent::ContractEnded {
+            id: contract.id,
+            participants: contract.participants,
+        });
+        Ok(())
+    }
+    
+    /// Find the interaction a conversation belongs to, returning its id, whether it was just
+    /// created, and the events for any interaction the speaker left to join it
+    ///
+    /// The speaker joins the NPC's current interaction if it has one. Otherwise a new one is
+    /// created with an id derived from the names and turn, so replays produce the same ids.
+    /// A speaker already in another interaction leaves it first, ending it if they were one
+    /// of only two participants.
+    fn join_or_start_conversation(&self, npc_name: &str, speaker: &str) -> Result<(String, bool, Vec<EngineEvent>)> {
+        self.update_state(|state| {
+            let npc = match state.npcs.get(npc_name) {
+                Some(npc) if npc.player_controlled => {
+                    return Err(Error::invalid(format!("'{}' is player-controlled", npc_name)));
+                }
+                Some(npc) => npc,
+                None => return Err(Error::NpcNotFound { name: npc_name.to_string() }),
+            };
+            // The speaker may have left the game while the NPC was answering
+            check_speaker(state, npc_name, speaker)?;
+            
+            let joining = npc.active_contract.clone().filter(|id| state.contracts.contains_key(id));
+            let mut events = Vec::new();
+            let speaker_contract = state.npcs.get(speaker).and_then(|npc| npc.active_contract.clone());
+            if speaker_contract.is_some() && speaker_contract != joining {
+                events.extend(leave_contract(state, speaker));
+            }
+            
+            if let Some(id) = joining {
+                if let Some(contract) = state.contracts.get_mut(&id) {
+                    if !contract.participants.iter().any(|p| p == speaker) {
+                        contract.participants.push(speaker.to_string());
+                    }
+                }
+                if let Some(speaker_npc) = state.npcs.get_mut(speaker) {
+                    speaker_npc.active_contract = Some(id.clone());
+                }
+                return Ok((id, false, events));
+            }
+            
+            // Skip ids already used by open contracts or earlier transcripts
+            let base = format!("talk_{}_{}_{}", speaker, npc_name, state.turn);
+            let mut id = base.clone();
+            let mut suffix = 2;
+            while state.contracts.contains_key(&id) || self.transcripts.path_for(&id).exists() {
+                id = format!("{}_{}", base, suffix);
+                suffix += 1;
+            }
+            
+            let contract = Contract {
+                id: id.clone(),
+                participants: vec![speaker.to_string(), npc_name.to_string()],
+                transcript_file: self.transcripts.path_for(&id).to_string_lossy().to_string(),
+            };
+            for participant in &contract.participants {
+                if let Some(npc) = state.npcs.get_mut(participant) {
+                    npc.active_contract = Some(id.clone());
+                }
+            }
+            state.contracts.insert(id.clone(), contract);
+            log::info!("  📜 Contract created: {}", id);
+            
+            Ok((id, true, events))
+        })
     }
     
     /// Update NPC memories based on what happened
     pub async fn update_memories(&self, intents: &[Intent], reality: &GmResponse) -> Result<()> {
+        self.update_memories_detailed(intents, reality).await?;
+        Ok(())
+    }
+    
+    /// Update memories, returning the NPCs whose update failed
+    async fn update_memories_detailed(&self, intents: &[Intent], reality: &GmResponse) -> Result<Vec<NpcError>> {
+        // Players' memories are the host game's business
+        let players: Vec<String> = self.get_state()
+            .npcs
+            .into_values()
+            .filter(|npc| npc.player_controlled)
+            .map(|npc| npc.name)
+            .collect();
+        let intents: Vec<&Intent> = intents.iter().filter(|i| !players.contains(&i.npc)).collect();
+        
         if intents.is_empty() {
             log::debug!("No intents to process for memory updates");
-            return Ok(());
+            return Ok(Vec::new());
         }
         
         log::info!("🧠 Updating memories for {} NPCs", intents.len());
@@ -269,7 +1188,7 @@ impl NpcEngine {
             .iter()
             .map(|intent| {
                 // Find which other NPCs were present
-                let other_npcs: Vec<String> = self.get_state()
+                let mut other_npcs: Vec<String> = self.get_state()
                     .npcs
                     .iter()
                     .filter(|(name, other_npc)| {
@@ -282,24 +1201,36 @@ impl NpcEngine {
                     })
                     .map(|(name, _)| name.clone())
                     .collect();
+                other_npcs.sort();
                 
                 MemoryUpdateInput {
                     npc_name: intent.npc.clone(),
-                    intent: intent.clone(),
-                    reality: reality.reality.clone(),
+                    intent: (*intent).clone(),
+                    reality: reality.reality_for(&intent.npc).to_string(),
                     other_npcs_present: other_npcs,
                 }
             })
             .collect();
         
         // Update each NPC's memories
+        let mut errors = Vec::new();
         for input in memory_inputs {
+            let npc = input.npc_name.clone();
             if let Err(e) = self.update_single_npc_memory(input).await {
-                log::error!("Failed to update memory: {}", e);
+                if e.is_fatal() {
+                    return Err(e);
+                }
+                log::error!("Failed to update memory for {}: {}", npc, e);
+                self.emit(EngineEvent::MemoryUpdateFailed { npc: npc.clone(), error: e.to_string() });
+                errors.push(NpcError {
+                    npc,
+                    phase: TurnPhase::Memories,
+                    error: e.to_string(),
+                });
             }
         }
         
-        Ok(())
+        Ok(errors)
     }
     
     async fn update_single_npc_memory(&self, input: MemoryUpdateInput) -> Result<()> {
@@ -318,19 +1249,17 @@ impl NpcEngine {
             &input.other_npcs_present,
         )?;
         
-        // Query LLM
-        let response = self.llm_client
-            .query(prompt, Path::new("."))
+        // Query LLM and parse memory update
+        let memory_update: MemoryUpdate = self
+            .query_json(prompt, &QueryContext::memory_update(npc_name))
             .await?;
         
-        // Parse memory update
-        let memory_update: MemoryUpdate = parser::extract_json(&response)?;
-        
-        // Apply the update to the memory system
+        // Apply the update to the memory system, dating new memories by game time
+        let clock = self.clock();
         current_memories.self_memories.immediate_context = memory_update.immediate_self_context.clone();
         
         if let Some(new_memory) = memory_update.new_self_memory {
-            current_memories.self_memories.add_recent_event(new_memory);
+            current_memories.self_memories.add_recent_event(format!("[{}] {}", clock, new_memory));
         }
         
         // Update relationship memories
@@ -340,7 +1269,8 @@ impl NpcEngine {
             relationship.immediate_context = rel_update.immediate_context;
             relationship.current_sentiment = rel_update.current_sentiment;
             
-            if let Some(new_memory) = rel_update.new_memory {
+            if let Some(mut new_memory) = rel_update.new_memory {
+                new_memory.timestamp = clock.timestamp();
                 relationship.add_memory(new_memory);
             }
             
@@ -357,41 +1287,187 @@ impl NpcEngine {
         self.save_npc_memories(npc_name, &current_memories)?;
         
         log::info!("  💭 {}: {}", npc_name, memory_update.immediate_self_context);
+        self.emit(EngineEvent::MemoryUpdated {
+            npc: npc_name.clone(),
+            immediate_context: memory_update.immediate_self_context,
+        });
         
         Ok(())
     }
     
     /// Load memories for an NPC
-    fn load_npc_memories(&self, npc_name: &str) -> Result<crate::memory::MemorySystem> {
+    fn load_npc_memories(&self, npc_name: &str) -> Result<MemorySystem> {
         let memory_path = self.data_path.join("npcs").join(npc_name).join("memories.json");
         
         if memory_path.exists() {
-            let content = std::fs::read_to_string(&memory_path)?;
-            let memories = serde_json::from_str(&content)?;
-            Ok(memories)
+            let content = std::fs::read_to_string(&memory_path).at(&memory_path)?;
+            serde_json::from_str(&content)
+                .map_err(|e| Error::invalid(format!("Failed to parse memories {:?}: {}", memory_path, e)))
         } else {
             // This shouldn't happen if ensure_memories_exist was called, but handle it anyway
-            Ok(crate::memory::MemorySystem::new())
+            Ok(MemorySystem::new())
         }
     }
     
-    /// Execute a complete turn (collect, resolve, update)
-    pub async fn execute_turn(&self) -> Result<GmResponse> {
+    /// Execute a complete turn (collect, resolve, update), reporting what happened
+    ///
+    /// NPCs that fail to produce an intent or update their memories are listed in the
+    /// report's errors; only a failed GM resolution fails the whole turn.
+    pub async fn execute_turn(&self) -> Result<TurnReport> {
         log::info!("Starting turn execution");
+        let turn_start = Instant::now();
+        *self.turn_calls.lock().unwrap() = Some(Vec::new());
+        self.emit(EngineEvent::TurnStarted { turn: self.get_state().turn + 1 });
+        
+        let result = self.run_turn(turn_start).await;
+        
+        // Stop recording calls whether or not the turn succeeded
+        let calls = self.turn_calls.lock().unwrap().take().unwrap_or_default();
+        let mut report = result?;
+        report.llm_calls = LlmCallStats::from_records(&calls);
+        report.repairs = calls.into_iter().filter(|r| !r.is_clean()).collect();
+        report.durations.total = turn_start.elapsed();
+        self.emit(EngineEvent::TurnFinished {
+            turn: report.turn,
+            duration: report.durations.total,
+        });
+        
+        Ok(report)
+    }
+    
+    async fn run_turn(&self, turn_start: Instant) -> Result<TurnReport> {
+        let mut durations = PhaseDurations::default();
         
         // Collect intents
-        let intents = self.collect_intents().await?;
+        let CollectedIntents { intents, mut errors, fallbacks, routines, idle } = self.collect_intents_detailed().await?;
+        durations.intents = turn_start.elapsed();
         log::info!("Collected {} intents", intents.len());
         
         // Resolve with GM
-        let reality = self.resolve_intents(intents.clone()).await?;
+        let phase_start = Instant::now();
+        let before = self.get_state();
+        let (reality, diagnostics) = self.resolve_intents_detailed(intents.clone()).await?;
+        self.follow_routines(&routines, &reality)?;
+        let changes = StateDiff::between(&before, &self.get_state());
+        durations.resolution = phase_start.elapsed();
         log::info!("GM resolved reality");
         
         // Update memories
-        self.update_memories(&intents, &reality).await?;
+        let phase_start = Instant::now();
+        errors.extend(self.update_memories_detailed(&intents, &reality).await?);
+        durations.memories = phase_start.elapsed();
         log::info!("Updated NPC memories");
         
-        Ok(reality)
+        let turn = self.update_state(|state| {
+            state.turn += 1;
+            state.clock.advance();
+            Ok(state.turn)
+        })?;
+        
+        if self.auto_snapshot {
+            // Hand-written state.json files are only replaced by an explicit save
+            self.snapshot()?.save(self.snapshots.path_for(turn))?;
+            if let Some(keep) = self.snapshot_retention {
+                self.snapshots.prune(keep)?;
+            }
+            log::debug!("Saved snapshot for turn {}", turn);
+        }
+        
+        Ok(TurnReport {
+            turn,
+            intents,
+            reality,
+            diagnostics,
+            changes,
+            errors,
+            fallbacks,
+            routines,
+            idle,
+            durations,
+            llm_calls: HashMap::new(),
+            repairs: Vec::new(),
+        })
+    }
+    
+    /// Capture the full world (state, memories and open transcripts) into a snapshot
+    pub fn snapshot(&self) -> Result<Snapshot> {
+        let state = self.get_state();
+        
+        let mut memories = HashMap::new();
+        for (name, npc) in &state.npcs {
+            if !npc.player_controlled {
+                memories.insert(name.clone(), self.load_npc_memories(name)?);
+            }
+        }
+        
+        let mut transcripts = HashMap::new();
+        for id in state.contracts.keys() {
+            // A contract may have been created without a transcript entry yet
+            if let Ok(transcript) = self.transcripts.load(id) {
+                transcripts.insert(id.clone(), transcript);
+            }
+        }
+        
+        Ok(Snapshot::new(state, memories, transcripts))
+    }
+    
+    /// Save the full world to a versioned snapshot file
+    ///
+    /// Each NPC's state.json is updated as well, so a fresh engine starts where this one left off.
+    pub fn save_snapshot(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.snapshot()?.save(path)?;
+        self.save_npc_states()
+    }
+    
+    /// Replace the current world with the contents of a snapshot file
+    pub fn load_snapshot(&self, path: impl AsRef<Path>) -> Result<()> {
+        let snapshot = Snapshot::load(path)?;
+        self.restore(snapshot)
+    }
+    
+    /// Replace the current world with a snapshot
+    pub fn restore(&self, snapshot: Snapshot) -> Result<()> {
+        for (name, memories) in &snapshot.memories {
+            self.save_npc_memories(name, memories)?;
+        }
+        
+        for transcript in snapshot.transcripts.values() {
+            self.transcripts.save(transcript)?;
+        }
+        
+        let mut state = snapshot.state;
+        
+        // Transcript paths depend on where the data directory lives now
+        for contract in state.contracts.values_mut() {
+            contract.transcript_file = self.transcripts
+                .path_for(&contract.id)
+                .to_string_lossy()
+                .to_string();
+        }
+        
+        log::info!("Restored snapshot from turn {} with {} NPCs", state.turn, state.npcs.len());
+        
+        // Everyone takes a fresh look around in the restored world
+        self.attention.lock().unwrap().clear();
+        
+        self.update_state(|current| {
+            *current = state;
+            Ok(())
+        })
+    }
+    
+    /// List the automatic per-turn snapshots, oldest first
+    pub fn list_snapshots(&self) -> Result<Vec<SnapshotInfo>> {
+        self.snapshots.list()
+    }
+    
+    /// Restore the automatic snapshot taken at the end of the given turn
+    pub fn restore_snapshot(&self, turn: u64) -> Result<()> {
+        let path = self.snapshots.path_for(turn);
+        if !path.exists() {
+            return Err(Error::SnapshotNotFound { turn });
+        }
+        self.load_snapshot(path)
     }
     
     /// Set the location and activity for an NPC
@@ -402,15 +1478,55 @@ impl NpcEngine {
                 npc.activity = activity.into();
                 Ok(())
             } else {
-                Err(anyhow::anyhow!("NPC '{}' not found", npc_name))
+                Err(Error::NpcNotFound { name: npc_name.to_string() })
             }
         })
     }
     
-    /// Initialize a new NPC with template files
-    pub fn init_npc(&self, name: &str) -> Result<()> {
-        // TODO: Create NPC directory and template files
-        Ok(())
+    /// Initialize a new NPC with template files and add it to the running game
+    ///
+    /// Creates `npcs/<name>/` containing a templated `personality.md`, an
+    /// `initial_memories.json` and, if a starting state is given, a `state.json`.
+    /// Fails if an NPC with this name already exists.
+    pub fn init_npc(&self, name: &str, start: Option<NpcStateFile>) -> Result<()> {
+        if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+            return Err(Error::invalid(format!("Invalid NPC name: '{}'", name)));
+        }
+        
+        let npc_dir = self.data_path.join("npcs").join(name);
+        if npc_dir.exists() || self.get_state().npcs.contains_key(name) {
+            return Err(Error::invalid(format!("NPC '{}' already exists at {:?}", name, npc_dir)));
+        }
+        
+        // Check the starting state before anything is written
+        if let Some(start) = &start {
+            start.validate()
+                .map_err(|e| Error::invalid(format!("Invalid starting state for '{}': {}", name, e)))?;
+        }
+        
+        log::info!("Initializing NPC: {}", name);
+        std::fs::create_dir_all(&npc_dir).at(&npc_dir)?;
+        
+        let personality_path = npc_dir.join("personality.md");
+        let personality = PERSONALITY_TEMPLATE.replace("{name}", name);
+        std::fs::write(&personality_path, personality).at(&personality_path)?;
+        
+        let memories_path = npc_dir.join("initial_memories.json");
+        let memories = MemorySystem::with_context(format!("{} is going about their day", name));
+        let json = serde_json::to_string_pretty(&memories)?;
+        std::fs::write(&memories_path, json).at(&memories_path)?;
+        
+        if let Some(start) = start {
+            let state_path = npc_dir.join("state.json");
+            let json = serde_json::to_string_pretty(&start)?;
+            std::fs::write(&state_path, json).at(&state_path)?;
+        }
+        
+        let npc = self.load_npc(name, &npc_dir)?;
+        self.update_state(|state| {
+            state.npcs.insert(name.to_string(), npc);
+            Ok(())
+        })
     }
     
     /// Load NPCs from the data directory
@@ -425,44 +1541,33 @@ impl NpcEngine {
         let mut npcs = HashMap::new();
         
         // Read all directories in the npcs folder
-        for entry in std::fs::read_dir(&npcs_dir)? {
-            let entry = entry?;
+        for entry in std::fs::read_dir(&npcs_dir).at(&npcs_dir)? {
+            let entry = entry.at(&npcs_dir)?;
             let path = entry.path();
             
             if path.is_dir() {
                 let npc_name = path.file_name()
                     .and_then(|n| n.to_str())
-                    .ok_or_else(|| anyhow::anyhow!("Invalid NPC directory name"))?;
+                    .ok_or_else(|| Error::invalid(format!("Invalid NPC directory name: {:?}", path)))?;
                 
                 log::info!("Loading NPC: {}", npc_name);
                 
                 // Check if personality.md exists
-                let personality_path = path.join("personality.md");
-                if !personality_path.exists() {
+                if !path.join("personality.md").exists() {
                     log::warn!("No personality.md found for NPC: {}", npc_name);
                     continue;
                 }
                 
-                // Create NPC with default values
-                let npc = Npc {
-                    name: npc_name.to_string(),
-                    location: "start".to_string(), // Default location
-                    activity: "idle".to_string(),   // Default activity
-                    folder_path: path.to_string_lossy().to_string(),
-                    active_contract: None,
-                    next_prompt: None,
-                };
-                
-                // Ensure memories exist (create from initial_memories.json if needed)
-                self.ensure_memories_exist(npc_name)?;
+                let npc = self.load_npc(npc_name, &path)?;
                 
                 npcs.insert(npc_name.to_string(), npc);
             }
         }
         
-        // Update the game state with loaded NPCs
+        // Update the game state with loaded NPCs, keeping any registered players
         self.update_state(|state| {
-            state.npcs = npcs;
+            state.npcs.retain(|_, npc| npc.player_controlled);
+            state.npcs.extend(npcs);
             Ok(())
         })?;
         
@@ -470,6 +1575,66 @@ impl NpcEngine {
         Ok(())
     }
     
+    /// Build an NPC from its folder, using state.json for the starting state if present
+    fn load_npc(&self, npc_name: &str, path: &Path) -> Result<Npc> {
+        let state_path = path.join("state.json");
+        let start = if state_path.exists() {
+            Self::read_state_file(&state_path)?
+        } else {
+            NpcStateFile::default()
+        };
+        
+        // Ensure memories exist (create from initial_memories.json if needed)
+        self.ensure_memories_exist(npc_name)?;
+        
+        if let Some(world) = &self.world {
+            if !world.contains(&start.location) {
+                log::warn!("{} starts at '{}', which isn't on the world map", npc_name, start.location);
+            }
+        }
+        
+        Ok(Npc {
+            name: npc_name.to_string(),
+            location: start.location,
+            activity: start.activity,
+            folder_path: path.to_string_lossy().to_string(),
+            active_contract: None,
+            next_prompt: None,
+            schedule: start.schedule,
+            attributes: start.attributes,
+            player_controlled: false,
+            travel: start.travel,
+        })
+    }
+    
+    /// Read and validate an NPC's state.json
+    fn read_state_file(path: &Path) -> Result<NpcStateFile> {
+        let content = std::fs::read_to_string(path).at(path)?;
+        let state: NpcStateFile = serde_json::from_str(&content)
+            .map_err(|e| Error::invalid(format!("Invalid NPC state file {:?}: {}", path, e)))?;
+        state.validate()
+            .map_err(|e| Error::invalid(format!("Invalid NPC state file {:?}: {}", path, e)))?;
+        Ok(state)
+    }
+    
+    /// Write every NPC's current location, activity, schedule and attributes back to its state.json
+    pub fn save_npc_states(&self) -> Result<()> {
+        for (name, npc) in &self.get_state().npcs {
+            // Players aren't loaded from disk, the host game registers them
+            if npc.player_controlled {
+                continue;
+            }
+            
+            let npc_dir = self.data_path.join("npcs").join(name);
+            std::fs::create_dir_all(&npc_dir).at(&npc_dir)?;
+            
+            let state_path = npc_dir.join("state.json");
+            let json = serde_json::to_string_pretty(&NpcStateFile::from(npc))?;
+            std::fs::write(&state_path, json).at(&state_path)?;
+        }
+        Ok(())
+    }
+    
     /// Ensure memories.json exists for an NPC, creating from initial_memories.json if needed
     fn ensure_memories_exist(&self, npc_name: &str) -> Result<()> {
         let npc_dir = self.data_path.join("npcs").join(npc_name);
@@ -479,20 +1644,21 @@ impl NpcEngine {
             let initial_path = npc_dir.join("initial_memories.json");
             if initial_path.exists() {
                 log::info!("Creating memories.json from initial_memories.json for {}", npc_name);
-                let content = std::fs::read_to_string(&initial_path)?;
+                let content = std::fs::read_to_string(&initial_path).at(&initial_path)?;
                 
                 // Validate it's valid JSON
-                let memories: crate::memory::MemorySystem = serde_json::from_str(&content)?;
+                let memories: MemorySystem = serde_json::from_str(&content)
+                    .map_err(|e| Error::invalid(format!("Failed to parse memories {:?}: {}", initial_path, e)))?;
                 
                 // Save as memories.json
                 let json = serde_json::to_string_pretty(&memories)?;
-                std::fs::write(&memory_path, json)?;
+                std::fs::write(&memory_path, json).at(&memory_path)?;
             } else {
                 log::info!("Creating empty memories.json for {}", npc_name);
                 // Create empty memory system
-                let memories = crate::memory::MemorySystem::new();
+                let memories = MemorySystem::new();
                 let json = serde_json::to_string_pretty(&memories)?;
-                std::fs::write(&memory_path, json)?;
+                std::fs::write(&memory_path, json).at(&memory_path)?;
             }
         }
         
@@ -500,16 +1666,161 @@ impl NpcEngine {
     }
     
     /// Save memories for an NPC
-    fn save_npc_memories(&self, npc_name: &str, memories: &crate::memory::MemorySystem) -> Result<()> {
+    fn save_npc_memories(&self, npc_name: &str, memories: &MemorySystem) -> Result<()> {
         let npc_dir = self.data_path.join("npcs").join(npc_name);
         
         // Create directory if it doesn't exist
-        std::fs::create_dir_all(&npc_dir)?;
+        std::fs::create_dir_all(&npc_dir).at(&npc_dir)?;
         
         let memory_path = npc_dir.join("memories.json");
         let json = serde_json::to_string_pretty(memories)?;
-        std::fs::write(memory_path, json)?;
+        std::fs::write(&memory_path, json).at(&memory_path)?;
         
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// Whether an NPC can be left to follow their schedule: alone, not in an interaction and
+/// not on their way somewhere else
+fn on_routine(npc: &Npc, game_state: &GameState) -> bool {
+    let Some(entry) = npc.scheduled(&game_state.clock) else {
+        return false;
+    };
+    
+    npc.active_contract.is_none()
+        && npc.travel.as_ref().is_none_or(|travel| travel.destination == entry.location)
+        && !game_state
+            .npcs
+            .values()
+            .any(|other| other.name != npc.name && other.location == npc.location)
+}
+
+/// An intent to keep doing whatever the NPC is doing, for NPCs who weren't (or couldn't be) asked
+fn continue_intent(npc: &Npc) -> Intent {
+    Intent {
+        npc: npc.name.clone(),
+        thought: String::new(),
+        action: format!("continues {}", npc.activity),
+        dialogue: None,
+    }
+}
+
+/// Check that someone talking to an NPC is another character in the game
+fn check_speaker(state: &GameState, npc_name: &str, speaker: &str) -> Result<()> {
+    if speaker == npc_name {
+        return Err(Error::invalid(format!("'{}' can't talk to themselves", npc_name)));
+    }
+    if !state.npcs.contains_key(speaker) {
+        return Err(Error::NpcNotFound { name: speaker.to_string() });
+    }
+    Ok(())
+}
+
+/// Take an NPC out of their interaction, ending it if fewer than two participants remain
+fn leave_contract(state: &mut GameState, npc_name: &str) -> Option<EngineEvent> {
+    let id = state.npcs.get_mut(npc_name)?.active_contract.take()?;
+    let contract = state.contracts.get_mut(&id)?;
+    contract.participants.retain(|p| p != npc_name);
+    if contract.participants.len() >= 2 {
+        return None;
+    }
+    
+    let mut participants = state.contracts.remove(&id)?.participants;
+    for participant in &participants {
+        if let Some(npc) = state.npcs.get_mut(participant) {
+            npc.active_contract = None;
+        }
+    }
+    participants.push(npc_name.to_string());
+    log::info!("  📜 Contract ended: {}", id);
+    Some(EngineEvent::ContractEnded { id, participants })
+}
+
+/// Move every travelling NPC one turn further along their route, in name order
+fn advance_travellers(state: &mut GameState) -> Vec<EngineEvent> {
+    let mut travellers: Vec<&mut Npc> = state.npcs.values_mut().filter(|npc| npc.travel.is_some()).collect();
+    travellers.sort_by(|a, b| a.name.cmp(&b.name));
+    
+    travellers
+        .into_iter()
+        .filter_map(|npc| {
+            let from = npc.location.clone();
+            let reached = npc.advance_travel()?;
+            log::info!("  🚶 {} reached {}", npc.name, reached);
+            Some(EngineEvent::NpcMoved { npc: npc.name.clone(), from, to: reached })
+        })
+        .collect()
+}
+
+/// The outcome of collecting intents, each list in NPC name order
+#[derive(Default)]
+struct CollectedIntents {
+    intents: Vec<Intent>,
+    errors: Vec<NpcError>,
+    /// NPCs given a "continues current activity" intent after the deadline passed
+    fallbacks: Vec<String>,
+    /// NPCs left to follow their schedule instead of being asked
+    routines: Vec<String>,
+    /// "Continues current activity" intents for NPCs the relevance gate let carry on
+    idle: Vec<Intent>,
+}
+
+/// What the relevance gate knows about an NPC from earlier turns
+struct Attention {
+    /// What the NPC saw when they were last asked
+    last_seen: Surroundings,
+    idle_turns: u64,
+    /// Whether the GM wrote the NPC a next prompt while they were idle
+    prompt_pending: bool,
+}
+
+/// Streams responses from the wrapped client, broadcasting dialogue as it is generated
+struct DialogueTap<'a> {
+    client: &'a dyn LlmClient,
+    events: &'a broadcast::Sender<EngineEvent>,
+}
+
+#[async_trait]
+impl LlmClient for DialogueTap<'_> {
+    async fn query(&self, prompt: String, working_dir: &Path) -> Result<String> {
+        self.client.query(prompt, working_dir).await
+    }
+
+    async fn query_with_context(
+        &self,
+        prompt: String,
+        working_dir: &Path,
+        context: &QueryContext,
+    ) -> Result<String> {
+        let mut fields = match context.role {
+            LlmRole::Intent | LlmRole::Dialogue => JsonFieldStream::intent_dialogue(),
+            LlmRole::Gm => JsonFieldStream::gm_dialogue(),
+            // Nothing in a memory update is worth showing early
+            LlmRole::MemoryUpdate => {
+                return self.client.query_with_context(prompt, working_dir, context).await;
+            }
+        };
+
+        let mut chunks = self.client.query_stream(prompt, working_dir, context).await?;
+        let mut response = String::new();
+        while let Some(chunk) = chunks.next().await {
+            let chunk = chunk?;
+            for delta in fields.push(&chunk) {
+                // GM dialogue sits under the speaker's name in the transcript details
+                let speaker = match delta.path.len() {
+                    len if len >= 2 => delta.path[len - 2].clone(),
+                    _ => context.npc.clone().unwrap_or_default(),
+                };
+                let _ = self.events.send(EngineEvent::DialogueChunk {
+                    context: context.clone(),
+                    speaker,
+                    text: delta.text,
+                    done: delta.done,
+                });
+            }
+            response.push_str(&chunk);
+        }
+
+        Ok(response)
+    }
+}
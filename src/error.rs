@@ -0,0 +1,141 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::validation::Diagnostic;
+
+/// Result type used throughout the library
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Everything that can go wrong in the library
+///
+/// LLM and parse errors keep the raw response so it can be logged or shown while debugging.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// The LLM didn't answer in time
+    #[error("LLM query timed out after {timeout:?}")]
+    LlmTimeout { timeout: Duration },
+
+    /// The LLM server couldn't be reached or returned an error status
+    #[error("LLM request failed: {message}")]
+    LlmHttp { status: Option<u16>, message: String },
+
+    /// Any other failure from an LLM client, e.g. a response in an unexpected shape
+    #[error("LLM query failed: {0}")]
+    Llm(String),
+
+    /// The LLM response didn't contain anything that looks like JSON
+    #[error("no JSON found in LLM response")]
+    NoJson { response: String },
+
+    /// The LLM response contained JSON that couldn't be parsed, even after repairs
+    #[error("LLM returned invalid JSON: {source}")]
+    ParseJson {
+        #[source]
+        source: serde_json::Error,
+        response: String,
+    },
+
+    /// The LLM response was valid JSON but not in the expected shape
+    #[error("LLM JSON has incorrect format for type {type_name}: {source}")]
+    SchemaMismatch {
+        type_name: &'static str,
+        #[source]
+        source: serde_json::Error,
+        response: String,
+    },
+
+    /// The GM response referenced things that don't exist, see [`ValidationPolicy`](crate::ValidationPolicy)
+    #[error("GM response failed validation: {}", join(.0))]
+    Validation(Vec<Diagnostic>),
+
+    /// A replayed run made a query the cassette didn't record, see [`ReplayClient`](crate::llm::ReplayClient)
+    ///
+    /// The run has gone somewhere the recording can't follow, so this is never retried,
+    /// handed to a fallback or skipped over: it fails the whole turn.
+    #[error("replay diverged from cassette {path:?}: {detail}")]
+    ReplayDiverged { path: PathBuf, detail: String },
+
+    #[error("NPC '{name}' not found")]
+    NpcNotFound { name: String },
+
+    #[error("no snapshot found for turn {turn}")]
+    SnapshotNotFound { turn: u64 },
+
+    /// Reading or writing a file failed
+    #[error("I/O error at {path:?}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// A data file or argument holds something the engine can't use
+    #[error("{0}")]
+    InvalidData(String),
+
+    /// Serializing or deserializing the library's own data failed
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+impl Error {
+    /// The raw LLM response that caused this error, if it came from one
+    pub fn raw_response(&self) -> Option<&str> {
+        match self {
+            Error::NoJson { response }
+            | Error::ParseJson { response, .. }
+            | Error::SchemaMismatch { response, .. } => Some(response),
+            _ => None,
+        }
+    }
+
+    /// Whether the LLM call itself failed, as opposed to its output being unusable
+    pub fn is_llm_error(&self) -> bool {
+        matches!(self, Error::LlmTimeout { .. } | Error::LlmHttp { .. } | Error::Llm(_))
+    }
+
+    /// Whether the LLM answered but its output couldn't be parsed into the expected type
+    pub fn is_parse_error(&self) -> bool {
+        matches!(
+            self,
+            Error::NoJson { .. } | Error::ParseJson { .. } | Error::SchemaMismatch { .. }
+        )
+    }
+
+    /// Whether the error means the run itself can't go on, rather than one call going wrong
+    pub fn is_fatal(&self) -> bool {
+        matches!(self, Error::ReplayDiverged { .. })
+    }
+
+    pub(crate) fn invalid(message: impl Into<String>) -> Self {
+        Error::InvalidData(message.into())
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(error: reqwest::Error) -> Self {
+        Error::LlmHttp {
+            status: error.status().map(|s| s.as_u16()),
+            message: error.to_string(),
+        }
+    }
+}
+
+/// Attach the path being worked on to I/O errors
+pub(crate) trait IoResultExt<T> {
+    fn at(self, path: impl AsRef<Path>) -> Result<T>;
+}
+
+impl<T> IoResultExt<T> for std::io::Result<T> {
+    fn at(self, path: impl AsRef<Path>) -> Result<T> {
+        self.map_err(|source| Error::Io {
+            path: path.as_ref().to_path_buf(),
+            source,
+        })
+    }
+}
+
+fn join(diagnostics: &[Diagnostic]) -> String {
+    diagnostics.iter().map(|d| d.to_string()).collect::<Vec<_>>().join("; ")
+}
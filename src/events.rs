@@ -0,0 +1,72 @@
+use serde::Serialize;
+use std::time::Duration;
+
+use crate::llm::QueryContext;
+use crate::types::{DialogueReply, GmResponse, Intent};
+use crate::validation::Diagnostic;
+
+/// Number of events buffered per subscriber before slow subscribers start missing events
+pub const DEFAULT_EVENT_CAPACITY: usize = 256;
+
+/// Something the engine did, broadcast to every subscriber of `NpcEngine::subscribe`
+///
+/// Subscribers that fall more than the channel capacity behind receive a `Lagged` error
+/// from `recv` and skip ahead; the engine never waits for them.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EngineEvent {
+    /// `execute_turn` started the given turn
+    TurnStarted { turn: u64 },
+    /// `execute_turn` finished the given turn
+    TurnFinished { turn: u64, duration: Duration },
+    /// An NPC decided what to do
+    IntentCollected { intent: Intent },
+    /// An NPC missed the intent deadline and continues their current activity with `intent`
+    IntentTimedOut { intent: Intent },
+    /// The relevance gate let an NPC continue their current activity with `intent` without asking them
+    NpcIdle { intent: Intent },
+    /// An NPC failed to produce an intent and sits this turn out
+    IntentFailed { npc: String, error: String },
+    /// The GM resolver failed and the fallback resolver resolved the intents instead
+    GmFallback { error: String },
+    /// The GM resolved the turn's intents; `response` is the response as applied
+    GmResolved { response: GmResponse, diagnostics: Vec<Diagnostic> },
+    /// An NPC changed location, including each stop along a journey
+    NpcMoved { npc: String, from: String, to: String },
+    /// An NPC set off for somewhere more than a turn away
+    TravelStarted { npc: String, destination: String, turns_left: u32 },
+    /// An NPC started doing something else
+    NpcActivityChanged { npc: String, from: String, to: String },
+    ContractCreated { id: String, participants: Vec<String> },
+    ContractUpdated { id: String },
+    ContractEnded { id: String, participants: Vec<String> },
+    /// An NPC answered someone through `NpcEngine::converse`
+    NpcReplied {
+        npc: String,
+        speaker: String,
+        utterance: String,
+        reply: DialogueReply,
+    },
+    /// Part of a line of dialogue as the LLM generates it, sent only when streaming is enabled
+    ///
+    /// The chunks for one speaker within one call concatenate to their full line, the last
+    /// having `done` set. A call that is retried streams again from the start.
+    DialogueChunk {
+        context: QueryContext,
+        speaker: String,
+        text: String,
+        done: bool,
+    },
+    /// An NPC's memories were updated; `immediate_context` is their new sense of what they're doing
+    MemoryUpdated { npc: String, immediate_context: String },
+    MemoryUpdateFailed { npc: String, error: String },
+    /// The engine is about to query the LLM
+    LlmCallStarted { context: QueryContext },
+    /// An LLM call finished, after any retries
+    LlmCallFinished {
+        context: QueryContext,
+        attempts: u32,
+        succeeded: bool,
+        duration: Duration,
+    },
+}
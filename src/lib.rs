@@ -9,20 +9,21 @@
 //! - **Intent System**: NPCs form intentions based on their state and memories
 //! - **GM Resolution**: Game Master system resolves intents into reality
 //! - **Contract System**: Multi-turn interactions between NPCs
-//! - **LLM Integration**: Built-in Ollama support for AI-driven behaviors
+//! - **LLM Integration**: Built-in Ollama and OpenAI-compatible (llama.cpp, vLLM) support for AI-driven behaviors
 //!
 //! ## Example
 //!
 //! ```rust,no_run
 //! use social_npc::{NpcEngine, llm::OllamaClient};
 //!
-//! # async fn example() -> anyhow::Result<()> {
+//! # async fn example() -> social_npc::Result<()> {
 //! // Create the engine with Ollama
 //! let llm = OllamaClient::new("llama3.2:latest");
 //! let mut engine = NpcEngine::new("./data", llm)?;
 //!
 //! // Execute a complete turn
-//! let result = engine.execute_turn().await?;
+//! let report = engine.execute_turn().await?;
+//! println!("{}", report.reality.reality);
 //!
 //! // Or run individual phases
 //! let intents = engine.collect_intents().await?;
@@ -31,16 +32,32 @@
 //! # }
 //! ```
 
+pub mod clock;
 pub mod engine;
+pub mod error;
+pub mod events;
 pub mod llm;
 pub mod memory;
 pub mod parser;
+pub mod partition;
+pub mod priority;
 pub mod prompts;
+pub mod relevance;
+pub mod report;
+pub mod resolver;
+pub mod retry;
+pub mod snapshot;
 pub mod traits;
+pub mod transcript;
 pub mod types;
+pub mod validation;
+pub mod world;
 
 // Re-export main types for convenience
+pub use clock::{TimeOfDay, WorldClock};
 pub use engine::NpcEngine;
+pub use error::{Error, Result};
+pub use events::EngineEvent;
 pub use memory::{
     FadeDecision, Memory, MemorySystem, MemoryUpdate, RelationshipMemory,
     RelationshipUpdate, SelfMemories,
@@ -50,9 +67,20 @@ pub use traits::{
     PerceptionResult, SocialInteraction,
 };
 pub use types::{
-    Contract, CurrentState, GameState, GmInput, GmResponse, Intent, MemoryUpdateInput,
-    Npc, NpcAction, StateChange, TranscriptEntry,
+    Contract, CurrentState, DialogueReply, GameState, GmInput, GmResponse, Intent, MemoryUpdateInput,
+    Leg, Npc, NpcAction, NpcStateFile, ScheduleEntry, StateChange, TranscriptEntry, Travel,
 };
+pub use parser::JsonRepair;
+pub use partition::Partition;
+pub use priority::{IntentPriority, NearPlayers};
+pub use relevance::{DefaultRelevanceGate, RelevanceContext, RelevanceGate, Surroundings, WakeReason};
+pub use resolver::{GmResolver, LlmResolver, ResolveContext, RuleBasedResolver};
+pub use report::{LlmCallStats, NpcChange, NpcError, PhaseDurations, StateDiff, TurnPhase, TurnReport};
+pub use retry::{RepairRecord, RetryPolicy};
+pub use snapshot::{Snapshot, SnapshotInfo, SNAPSHOT_VERSION};
+pub use transcript::{Transcript, TranscriptRecord, TranscriptStore};
+pub use validation::{Diagnostic, ValidationPolicy};
+pub use world::{Location, World};
 
 /// Library version
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
\ No newline at end of file
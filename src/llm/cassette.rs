@@ -0,0 +1,207 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use super::{LlmClient, QueryContext};
+use crate::error::{Error, IoResultExt, Result};
+
+/// One recorded prompt/response pair, stored as a line of a JSONL cassette
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CassetteEntry {
+    pub context: Option<QueryContext>,
+    pub prompt: String,
+    /// The response text, if the query succeeded
+    pub response: Option<String>,
+    /// The error message, if the query failed
+    pub error: Option<String>,
+}
+
+/// Wraps a real client and appends every prompt/response pair to a JSONL cassette
+///
+/// ```rust,no_run
+/// use social_npc::{NpcEngine, llm::{OllamaClient, RecordingClient}};
+///
+/// # fn example() -> social_npc::Result<()> {
+/// let llm = RecordingClient::create("bug_1234.jsonl", OllamaClient::new("llama3.2:latest"))?;
+/// let engine = NpcEngine::new("./data", llm)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct RecordingClient<C> {
+    path: PathBuf,
+    inner: C,
+    writer: Mutex<BufWriter<File>>,
+}
+
+impl<C: LlmClient> RecordingClient<C> {
+    /// Start recording into a new cassette, replacing any existing file
+    pub fn create(path: impl AsRef<Path>, inner: C) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).at(parent)?;
+        }
+        let file = File::create(&path).at(&path)?;
+
+        Ok(Self {
+            path,
+            inner,
+            writer: Mutex::new(BufWriter::new(file)),
+        })
+    }
+
+    fn record(&self, context: Option<&QueryContext>, prompt: String, result: &Result<String>) -> Result<()> {
+        let entry = CassetteEntry {
+            context: context.cloned(),
+            prompt,
+            response: result.as_ref().ok().cloned(),
+            error: result.as_ref().err().map(|e| e.to_string()),
+        };
+
+        let mut writer = self.writer.lock().unwrap();
+        serde_json::to_writer(&mut *writer, &entry)?;
+        writer.write_all(b"\n").at(&self.path)?;
+        // Flush every entry so a crashing run still leaves a usable cassette
+        writer.flush().at(&self.path)?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<C: LlmClient> LlmClient for RecordingClient<C> {
+    async fn query(&self, prompt: String, working_dir: &Path) -> Result<String> {
+        let result = self.inner.query(prompt.clone(), working_dir).await;
+        self.record(None, prompt, &result)?;
+        result
+    }
+
+    async fn query_with_context(
+        &self,
+        prompt: String,
+        working_dir: &Path,
+        context: &QueryContext,
+    ) -> Result<String> {
+        let result = self.inner.query_with_context(prompt.clone(), working_dir, context).await;
+        self.record(Some(context), prompt, &result)?;
+        result
+    }
+}
+
+/// Replays a cassette recorded by `RecordingClient` without contacting a model
+///
+/// Entries are replayed in recorded order per call site and NPC, so NPCs queried in
+/// parallel may arrive in any order. A query whose prompt differs from the recording, or
+/// that was never recorded, fails with `Error::ReplayDiverged` pointing at the first
+/// differing line; that error is never retried and fails the whole turn.
+pub struct ReplayClient {
+    path: PathBuf,
+    queues: Mutex<HashMap<Option<QueryContext>, VecDeque<CassetteEntry>>>,
+}
+
+impl ReplayClient {
+    /// Load a cassette for replay
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let content = fs::read_to_string(&path).at(&path)?;
+
+        let mut queues: HashMap<Option<QueryContext>, VecDeque<CassetteEntry>> = HashMap::new();
+        for (line_number, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry: CassetteEntry = serde_json::from_str(line).map_err(|e| {
+                Error::invalid(format!("Invalid cassette entry at {:?} line {}: {}", path, line_number + 1, e))
+            })?;
+            queues.entry(entry.context.clone()).or_default().push_back(entry);
+        }
+
+        Ok(Self {
+            path,
+            queues: Mutex::new(queues),
+        })
+    }
+
+    /// Number of recorded queries that have not been replayed yet
+    pub fn remaining(&self) -> usize {
+        self.queues.lock().unwrap().values().map(VecDeque::len).sum()
+    }
+
+    /// Fail if any recorded query was never replayed
+    pub fn assert_exhausted(&self) -> Result<()> {
+        match self.remaining() {
+            0 => Ok(()),
+            n => Err(Error::invalid(format!("Cassette {:?} has {} unreplayed entries", self.path, n))),
+        }
+    }
+
+    fn replay(&self, prompt: &str, context: Option<&QueryContext>) -> Result<String> {
+        let mut queues = self.queues.lock().unwrap();
+        let queue = queues.get_mut(&context.cloned());
+        let Some(entry) = queue.as_ref().and_then(|queue| queue.front()) else {
+            return Err(Error::ReplayDiverged {
+                path: self.path.clone(),
+                detail: format!("no more recorded responses for {:?}", context),
+            });
+        };
+
+        // A mismatch leaves the entry in place so the cassette still shows where replay stopped
+        if entry.prompt != prompt {
+            return Err(Error::ReplayDiverged {
+                path: self.path.clone(),
+                detail: format!("prompt for {:?} differs: {}", context, describe_divergence(&entry.prompt, prompt)),
+            });
+        }
+        let entry = queue.and_then(VecDeque::pop_front).expect("entry checked above");
+        drop(queues);
+
+        match (entry.response, entry.error) {
+            (Some(response), _) => Ok(response),
+            (None, Some(error)) => Err(Error::Llm(error)),
+            (None, None) => Err(Error::invalid(format!(
+                "Cassette entry for {:?} has neither response nor error",
+                context
+            ))),
+        }
+    }
+}
+
+/// Describe the first line where two prompts differ
+fn describe_divergence(recorded: &str, actual: &str) -> String {
+    let mut recorded_lines = recorded.lines();
+    let mut actual_lines = actual.lines();
+    let mut line_number = 1;
+
+    loop {
+        match (recorded_lines.next(), actual_lines.next()) {
+            (Some(r), Some(a)) if r == a => line_number += 1,
+            (None, None) => return "the prompts differ only in line endings".to_string(),
+            (r, a) => {
+                return format!(
+                    "line {} was {:?} when recorded but is now {:?}",
+                    line_number,
+                    r.unwrap_or("<end of prompt>"),
+                    a.unwrap_or("<end of prompt>")
+                )
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl LlmClient for ReplayClient {
+    async fn query(&self, prompt: String, _working_dir: &Path) -> Result<String> {
+        self.replay(&prompt, None)
+    }
+
+    async fn query_with_context(
+        &self,
+        prompt: String,
+        _working_dir: &Path,
+        context: &QueryContext,
+    ) -> Result<String> {
+        self.replay(&prompt, Some(context))
+    }
+}
@@ -0,0 +1,245 @@
+use async_trait::async_trait;
+use regex::Regex;
+use serde::Serialize;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use super::{LlmClient, LlmRole, QueryContext};
+use crate::error::{Error, Result};
+
+/// A canned reply from the mock client
+#[derive(Debug, Clone)]
+pub enum MockResponse {
+    /// Return this text as the LLM response
+    Text(String),
+    /// Fail the query with this error message
+    Error(String),
+    /// Wait for the duration, then fail the query as a timeout
+    Timeout(Duration),
+    /// Wait for the duration, then return the text
+    Delayed(Duration, String),
+}
+
+impl MockResponse {
+    /// Respond with the JSON serialization of a value
+    pub fn json(value: &impl Serialize) -> Self {
+        Self::Text(serde_json::to_string(value).expect("mock response must serialize to JSON"))
+    }
+}
+
+impl From<&str> for MockResponse {
+    fn from(text: &str) -> Self {
+        Self::Text(text.to_string())
+    }
+}
+
+impl From<String> for MockResponse {
+    fn from(text: String) -> Self {
+        Self::Text(text)
+    }
+}
+
+/// Decides which queries a mock rule answers
+#[derive(Debug, Clone)]
+pub enum MockMatcher {
+    /// Every query
+    Any,
+    /// Queries from one engine call site
+    Role(LlmRole),
+    /// Queries built for one NPC
+    Npc(String),
+    /// Queries from one call site for one NPC
+    NpcRole(String, LlmRole),
+    /// Queries whose prompt matches the regex
+    Prompt(Regex),
+}
+
+impl MockMatcher {
+    fn matches(&self, prompt: &str, context: Option<&QueryContext>) -> bool {
+        match self {
+            MockMatcher::Any => true,
+            MockMatcher::Role(role) => context.is_some_and(|c| c.role == *role),
+            MockMatcher::Npc(npc) => context.is_some_and(|c| c.npc.as_deref() == Some(npc)),
+            MockMatcher::NpcRole(npc, role) => context
+                .is_some_and(|c| c.role == *role && c.npc.as_deref() == Some(npc)),
+            MockMatcher::Prompt(regex) => regex.is_match(prompt),
+        }
+    }
+}
+
+struct MockRule {
+    matcher: MockMatcher,
+    responses: Vec<MockResponse>,
+    calls: usize,
+}
+
+impl MockRule {
+    /// Responses are handed out in order, the last one repeating forever
+    fn next_response(&mut self) -> MockResponse {
+        let index = self.calls.min(self.responses.len() - 1);
+        self.calls += 1;
+        self.responses[index].clone()
+    }
+}
+
+/// A query received by the mock client
+#[derive(Debug, Clone)]
+pub struct RecordedQuery {
+    pub prompt: String,
+    /// None when the query came through `query` rather than `query_with_context`
+    pub context: Option<QueryContext>,
+}
+
+/// Scripted LLM client for deterministic tests of the engine
+///
+/// Rules are checked in the order they were added and the first match answers,
+/// so add specific rules (an NPC) before general ones (a role).
+///
+/// ```rust
+/// use social_npc::llm::{LlmRole, MockLlmClient, MockResponse};
+/// use std::time::Duration;
+///
+/// let mock = MockLlmClient::new()
+///     .on_npc("alice", r#"{"npc": "alice", "thought": "...", "action": "wave", "dialogue": null}"#)
+///     .on_npc("bob", MockResponse::Timeout(Duration::from_millis(10)))
+///     .on_role(LlmRole::Gm, r#"{"reality": "...", "state_changes": [], "contracts": [], "next_prompts": {}}"#);
+/// ```
+pub struct MockLlmClient {
+    rules: Mutex<Vec<MockRule>>,
+    received: Mutex<Vec<RecordedQuery>>,
+}
+
+impl MockLlmClient {
+    pub fn new() -> Self {
+        Self {
+            rules: Mutex::new(Vec::new()),
+            received: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Answer matching queries with the given response
+    pub fn respond(self, matcher: MockMatcher, response: impl Into<MockResponse>) -> Self {
+        self.respond_sequence(matcher, vec![response.into()])
+    }
+
+    /// Answer successive matching queries with the responses in order, repeating the last one
+    ///
+    /// # Panics
+    ///
+    /// Panics if `responses` is empty.
+    pub fn respond_sequence(self, matcher: MockMatcher, responses: Vec<MockResponse>) -> Self {
+        assert!(!responses.is_empty(), "mock rule needs at least one response");
+        self.rules.lock().unwrap().push(MockRule {
+            matcher,
+            responses,
+            calls: 0,
+        });
+        self
+    }
+
+    /// Answer every query from a call site
+    pub fn on_role(self, role: LlmRole, response: impl Into<MockResponse>) -> Self {
+        self.respond(MockMatcher::Role(role), response)
+    }
+
+    /// Answer every query built for an NPC
+    pub fn on_npc(self, npc: impl Into<String>, response: impl Into<MockResponse>) -> Self {
+        self.respond(MockMatcher::Npc(npc.into()), response)
+    }
+
+    /// Answer queries from a call site for a single NPC
+    pub fn on_npc_role(
+        self,
+        npc: impl Into<String>,
+        role: LlmRole,
+        response: impl Into<MockResponse>,
+    ) -> Self {
+        self.respond(MockMatcher::NpcRole(npc.into(), role), response)
+    }
+
+    /// Answer queries whose prompt matches a regex
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pattern` is not a valid regex.
+    pub fn on_prompt(self, pattern: &str, response: impl Into<MockResponse>) -> Self {
+        let regex = Regex::new(pattern).expect("invalid mock prompt pattern");
+        self.respond(MockMatcher::Prompt(regex), response)
+    }
+
+    /// Answer any query not matched by an earlier rule
+    pub fn with_default(self, response: impl Into<MockResponse>) -> Self {
+        self.respond(MockMatcher::Any, response)
+    }
+
+    /// All queries received so far, in the order they arrived
+    pub fn received(&self) -> Vec<RecordedQuery> {
+        self.received.lock().unwrap().clone()
+    }
+
+    /// Queries received so far from one call site
+    pub fn received_for(&self, role: LlmRole) -> Vec<RecordedQuery> {
+        self.received()
+            .into_iter()
+            .filter(|q| q.context.as_ref().is_some_and(|c| c.role == role))
+            .collect()
+    }
+
+    /// Forget the queries received so far
+    pub fn clear_received(&self) {
+        self.received.lock().unwrap().clear();
+    }
+
+    async fn answer(&self, prompt: String, context: Option<&QueryContext>) -> Result<String> {
+        let response = {
+            let mut rules = self.rules.lock().unwrap();
+            let response = rules
+                .iter_mut()
+                .find(|rule| rule.matcher.matches(&prompt, context))
+                .map(|rule| rule.next_response());
+
+            self.received.lock().unwrap().push(RecordedQuery {
+                prompt,
+                context: context.cloned(),
+            });
+
+            response.ok_or_else(|| Error::Llm(format!("MockLlmClient has no response for query {:?}", context)))?
+        };
+
+        match response {
+            MockResponse::Text(text) => Ok(text),
+            MockResponse::Error(message) => Err(Error::Llm(message)),
+            MockResponse::Timeout(duration) => {
+                tokio::time::sleep(duration).await;
+                Err(Error::LlmTimeout { timeout: duration })
+            }
+            MockResponse::Delayed(duration, text) => {
+                tokio::time::sleep(duration).await;
+                Ok(text)
+            }
+        }
+    }
+}
+
+impl Default for MockLlmClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl LlmClient for MockLlmClient {
+    async fn query(&self, prompt: String, _working_dir: &Path) -> Result<String> {
+        self.answer(prompt, None).await
+    }
+
+    async fn query_with_context(
+        &self,
+        prompt: String,
+        _working_dir: &Path,
+        context: &QueryContext,
+    ) -> Result<String> {
+        self.answer(prompt, Some(context)).await
+    }
+}
@@ -1,12 +1,158 @@
+pub mod cassette;
+pub mod mock;
 pub mod ollama;
+pub mod openai;
+pub mod rate_limit;
+pub mod router;
+mod stream;
 
-use anyhow::Result;
 use async_trait::async_trait;
+use futures::Stream;
+use serde::{Deserialize, Serialize};
 use std::path::Path;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use crate::error::Result;
+
+/// The engine call site a prompt was built for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LlmRole {
+    /// An NPC deciding what to do next
+    Intent,
+    /// The GM resolving intents into reality
+    Gm,
+    /// An NPC updating its memories after a turn
+    MemoryUpdate,
+    /// An NPC answering someone directly through `NpcEngine::converse`
+    Dialogue,
+}
+
+/// Describes what a query is for, so clients can route, record or tune requests by call site
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct QueryContext {
+    pub role: LlmRole,
+    /// The NPC the prompt was built for, if any
+    pub npc: Option<String>,
+}
+
+impl QueryContext {
+    pub fn intent(npc: impl Into<String>) -> Self {
+        Self {
+            role: LlmRole::Intent,
+            npc: Some(npc.into()),
+        }
+    }
+
+    pub fn gm() -> Self {
+        Self {
+            role: LlmRole::Gm,
+            npc: None,
+        }
+    }
+
+    pub fn memory_update(npc: impl Into<String>) -> Self {
+        Self {
+            role: LlmRole::MemoryUpdate,
+            npc: Some(npc.into()),
+        }
+    }
+
+    pub fn dialogue(npc: impl Into<String>) -> Self {
+        Self {
+            role: LlmRole::Dialogue,
+            npc: Some(npc.into()),
+        }
+    }
+}
+
+/// Pieces of an LLM response in the order they were generated
+pub type TokenStream = Pin<Box<dyn Stream<Item = Result<String>> + Send>>;
 
 #[async_trait]
 pub trait LlmClient: Send + Sync {
     async fn query(&self, prompt: String, working_dir: &Path) -> Result<String>;
+
+    /// Query with knowledge of which call site and NPC the prompt belongs to
+    ///
+    /// The engine always calls this method. The default ignores the context and calls `query`.
+    async fn query_with_context(
+        &self,
+        prompt: String,
+        working_dir: &Path,
+        _context: &QueryContext,
+    ) -> Result<String> {
+        self.query(prompt, working_dir).await
+    }
+
+    /// Stream the response as it is generated; concatenating the chunks gives the full response
+    ///
+    /// The default waits for `query_with_context` and yields the whole response as a single chunk.
+    async fn query_stream(
+        &self,
+        prompt: String,
+        working_dir: &Path,
+        context: &QueryContext,
+    ) -> Result<TokenStream> {
+        let response = self.query_with_context(prompt, working_dir, context).await?;
+        Ok(Box::pin(futures::stream::once(async move { Ok(response) })))
+    }
+}
+
+/// Lets a client be shared, e.g. to keep a handle on a mock after giving it to the engine
+#[async_trait]
+impl<T: LlmClient + ?Sized> LlmClient for Arc<T> {
+    async fn query(&self, prompt: String, working_dir: &Path) -> Result<String> {
+        (**self).query(prompt, working_dir).await
+    }
+
+    async fn query_with_context(
+        &self,
+        prompt: String,
+        working_dir: &Path,
+        context: &QueryContext,
+    ) -> Result<String> {
+        (**self).query_with_context(prompt, working_dir, context).await
+    }
+
+    async fn query_stream(
+        &self,
+        prompt: String,
+        working_dir: &Path,
+        context: &QueryContext,
+    ) -> Result<TokenStream> {
+        (**self).query_stream(prompt, working_dir, context).await
+    }
 }
 
-pub use ollama::OllamaClient;
\ No newline at end of file
+pub use cassette::{CassetteEntry, RecordingClient, ReplayClient};
+pub use mock::{MockLlmClient, MockMatcher, MockResponse, RecordedQuery};
+pub use ollama::{KeepAlive, OllamaClient, OllamaClientConfig, OllamaOptions};
+pub use openai::OpenAiCompatibleClient;
+pub use rate_limit::RateLimitedClient;
+pub use router::LlmRouter;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    /// A client that only implements `query`
+    struct Echo;
+
+    #[async_trait]
+    impl LlmClient for Echo {
+        async fn query(&self, prompt: String, _working_dir: &Path) -> Result<String> {
+            Ok(format!("echo: {}", prompt))
+        }
+    }
+
+    #[tokio::test]
+    async fn query_stream_defaults_to_the_whole_response_at_once() {
+        let stream = Echo.query_stream("hi".to_string(), Path::new("."), &QueryContext::gm()).await.unwrap();
+
+        let chunks: Vec<String> = stream.map(|chunk| chunk.unwrap()).collect().await;
+        assert_eq!(chunks, ["echo: hi"]);
+    }
+}
@@ -1,29 +1,352 @@
-use anyhow::{anyhow, Result};
 use async_trait::async_trait;
+use futures::{StreamExt, TryStreamExt};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::Path;
+use std::time::Duration;
 
-use super::LlmClient;
+use super::{LlmClient, LlmRole, QueryContext, TokenStream};
+use crate::error::{Error, Result};
 
-pub struct OllamaClient {
-    model: String,
-    base_url: String,
+/// Sampling options sent to Ollama; fields left as None use the model's defaults
+///
+/// Also used as per-call overrides, where only the fields that are set replace the client's options.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct OllamaOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_ctx: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repeat_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
 }
 
-impl OllamaClient {
+impl OllamaOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    pub fn with_top_p(mut self, top_p: f32) -> Self {
+        self.top_p = Some(top_p);
+        self
+    }
+
+    pub fn with_top_k(mut self, top_k: u32) -> Self {
+        self.top_k = Some(top_k);
+        self
+    }
+
+    pub fn with_seed(mut self, seed: i64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn with_num_ctx(mut self, num_ctx: u32) -> Self {
+        self.num_ctx = Some(num_ctx);
+        self
+    }
+
+    pub fn with_repeat_penalty(mut self, repeat_penalty: f32) -> Self {
+        self.repeat_penalty = Some(repeat_penalty);
+        self
+    }
+
+    pub fn with_stop(mut self, stop: Vec<String>) -> Self {
+        self.stop = Some(stop);
+        self
+    }
+
+    /// Returns these options with every field set in `overrides` replaced
+    pub fn merged_with(&self, overrides: &OllamaOptions) -> OllamaOptions {
+        OllamaOptions {
+            temperature: overrides.temperature.or(self.temperature),
+            top_p: overrides.top_p.or(self.top_p),
+            top_k: overrides.top_k.or(self.top_k),
+            seed: overrides.seed.or(self.seed),
+            num_ctx: overrides.num_ctx.or(self.num_ctx),
+            repeat_penalty: overrides.repeat_penalty.or(self.repeat_penalty),
+            stop: overrides.stop.clone().or_else(|| self.stop.clone()),
+        }
+    }
+}
+
+/// How long Ollama keeps the model loaded after a request
+///
+/// Ollama takes either a duration string with a unit, such as "10m" or "24h", or a number of
+/// seconds, where a negative number keeps the model loaded indefinitely and 0 unloads it at once.
+/// A bare "-1" string has no unit and is rejected, so use `KeepAlive::forever()` instead.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum KeepAlive {
+    Duration(String),
+    Seconds(i64),
+}
+
+impl KeepAlive {
+    /// Keep the model loaded until Ollama stops
+    pub fn forever() -> Self {
+        KeepAlive::Seconds(-1)
+    }
+
+    /// Unload the model as soon as the request is done
+    pub fn unload() -> Self {
+        KeepAlive::Seconds(0)
+    }
+}
+
+impl From<&str> for KeepAlive {
+    fn from(duration: &str) -> Self {
+        KeepAlive::Duration(duration.to_string())
+    }
+}
+
+impl From<String> for KeepAlive {
+    fn from(duration: String) -> Self {
+        KeepAlive::Duration(duration)
+    }
+}
+
+impl From<Duration> for KeepAlive {
+    fn from(duration: Duration) -> Self {
+        KeepAlive::Seconds(duration.as_secs().min(i64::MAX as u64) as i64)
+    }
+}
+
+/// Configuration for an `OllamaClient`
+///
+/// ```rust
+/// use social_npc::llm::{LlmRole, OllamaClient, OllamaClientConfig, OllamaOptions};
+/// use std::time::Duration;
+///
+/// let config = OllamaClientConfig::new("llama3.2:latest")
+///     .with_temperature(0.8)
+///     .with_num_ctx(8192)
+///     .with_seed(42)
+///     .with_timeout(Duration::from_secs(120))
+///     // Keep the GM consistent
+///     .with_role_options(LlmRole::Gm, OllamaOptions::new().with_temperature(0.2));
+/// let client = OllamaClient::from_config(config);
+/// ```
+#[derive(Debug, Clone)]
+pub struct OllamaClientConfig {
+    pub model: String,
+    pub base_url: String,
+    pub options: OllamaOptions,
+    /// Overrides applied on top of `options` for queries from a given call site
+    pub role_options: HashMap<LlmRole, OllamaOptions>,
+    /// Response format requested from Ollama, "json" unless disabled
+    pub format: Option<String>,
+    /// How long Ollama keeps the model loaded after a request, its own default if None
+    pub keep_alive: Option<KeepAlive>,
+    pub timeout: Duration,
+    /// Reuse HTTP connections between queries instead of opening a new one each time
+    pub reuse_connections: bool,
+}
+
+impl OllamaClientConfig {
     pub fn new(model: impl Into<String>) -> Self {
         Self {
             model: model.into(),
             base_url: "http://localhost:11434".to_string(),
+            options: OllamaOptions::new().with_temperature(0.7).with_top_p(0.9),
+            role_options: HashMap::new(),
+            format: Some("json".to_string()),
+            keep_alive: None,
+            timeout: Duration::from_secs(60),
+            reuse_connections: true,
         }
     }
 
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.options.temperature = Some(temperature);
+        self
+    }
+
+    pub fn with_top_p(mut self, top_p: f32) -> Self {
+        self.options.top_p = Some(top_p);
+        self
+    }
+
+    pub fn with_top_k(mut self, top_k: u32) -> Self {
+        self.options.top_k = Some(top_k);
+        self
+    }
+
+    pub fn with_seed(mut self, seed: i64) -> Self {
+        self.options.seed = Some(seed);
+        self
+    }
+
+    pub fn with_num_ctx(mut self, num_ctx: u32) -> Self {
+        self.options.num_ctx = Some(num_ctx);
+        self
+    }
+
+    pub fn with_repeat_penalty(mut self, repeat_penalty: f32) -> Self {
+        self.options.repeat_penalty = Some(repeat_penalty);
+        self
+    }
+
+    pub fn with_stop(mut self, stop: Vec<String>) -> Self {
+        self.options.stop = Some(stop);
+        self
+    }
+
+    /// Override options for every query from one call site
+    pub fn with_role_options(mut self, role: LlmRole, options: OllamaOptions) -> Self {
+        self.role_options.insert(role, options);
+        self
+    }
+
+    /// Set the response format, or None to let the model answer in free text
+    pub fn with_format(mut self, format: Option<String>) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Keep the model loaded for e.g. "10m", `Duration::from_secs(600)` or `KeepAlive::forever()`
+    pub fn with_keep_alive(mut self, keep_alive: impl Into<KeepAlive>) -> Self {
+        self.keep_alive = Some(keep_alive.into());
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_connection_reuse(mut self, reuse: bool) -> Self {
+        self.reuse_connections = reuse;
+        self
+    }
+}
+
+pub struct OllamaClient {
+    config: OllamaClientConfig,
+    client: reqwest::Client,
+}
+
+impl OllamaClient {
+    pub fn new(model: impl Into<String>) -> Self {
+        Self::from_config(OllamaClientConfig::new(model))
+    }
+
     pub fn with_url(model: impl Into<String>, base_url: impl Into<String>) -> Self {
-        Self {
-            model: model.into(),
-            base_url: base_url.into(),
+        Self::from_config(OllamaClientConfig::new(model).with_base_url(base_url))
+    }
+
+    pub fn from_config(config: OllamaClientConfig) -> Self {
+        let mut builder = reqwest::Client::builder();
+        if !config.reuse_connections {
+            builder = builder.pool_max_idle_per_host(0);
+        }
+        let client = builder.build().unwrap_or_else(|e| {
+            log::warn!("Failed to build configured HTTP client, using defaults: {}", e);
+            reqwest::Client::new()
+        });
+
+        Self { config, client }
+    }
+
+    pub fn config(&self) -> &OllamaClientConfig {
+        &self.config
+    }
+
+    /// Query with options overriding the client's configured ones for this call only
+    pub async fn query_with_options(&self, prompt: String, overrides: &OllamaOptions) -> Result<String> {
+        let request = self.build_request(prompt, overrides, false);
+
+        let timeout = self.config.timeout;
+        tokio::time::timeout(timeout, self.send(&request))
+            .await
+            .map_err(|_| Error::LlmTimeout { timeout })?
+    }
+
+    /// Stream a response with options overriding the client's configured ones for this call only
+    ///
+    /// The configured timeout applies to the wait for each chunk rather than the whole response.
+    pub async fn stream_with_options(&self, prompt: String, overrides: &OllamaOptions) -> Result<TokenStream> {
+        let request = self.build_request(prompt, overrides, true);
+
+        let timeout = self.config.timeout;
+        let response = tokio::time::timeout(timeout, self.post(&request))
+            .await
+            .map_err(|_| Error::LlmTimeout { timeout })??;
+
+        // Ollama sends one JSON object per line, each holding the next piece of the response
+        let chunks = super::stream::body_lines(response.bytes_stream(), timeout)
+            .map(|line| {
+                let chunk: OllamaStreamChunk = serde_json::from_str(&line?)
+                    .map_err(|e| Error::Llm(format!("Failed to parse Ollama stream chunk: {}", e)))?;
+                match chunk.error {
+                    Some(error) => Err(Error::Llm(format!("Ollama stream failed: {}", error))),
+                    None => Ok(chunk.response),
+                }
+            })
+            .try_filter(|chunk| futures::future::ready(!chunk.is_empty()));
+
+        Ok(Box::pin(chunks))
+    }
+
+    fn build_request(&self, prompt: String, overrides: &OllamaOptions, stream: bool) -> OllamaRequest {
+        log::debug!("Ollama query to model: {}", self.config.model);
+        log::debug!("Prompt length: {} chars", prompt.len());
+
+        OllamaRequest {
+            model: self.config.model.clone(),
+            prompt,
+            stream,
+            format: self.config.format.clone(),
+            keep_alive: self.config.keep_alive.clone(),
+            options: self.config.options.merged_with(overrides),
         }
     }
+
+    async fn send(&self, request: &OllamaRequest) -> Result<String> {
+        let response = self.post(request).await?;
+
+        let ollama_response: OllamaResponse = response.json().await
+            .map_err(|e| Error::Llm(format!("Failed to parse Ollama response: {}", e)))?;
+
+        Ok(ollama_response.response)
+    }
+
+    async fn post(&self, request: &OllamaRequest) -> Result<reqwest::Response> {
+        let response = self.client
+            .post(format!("{}/api/generate", self.config.base_url))
+            .json(request)
+            .send()
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await?;
+            return Err(Error::LlmHttp {
+                status: Some(status.as_u16()),
+                message: format!("Ollama request failed ({}): {}", status, error_text),
+            });
+        }
+
+        Ok(response)
+    }
 }
 
 #[derive(Serialize)]
@@ -31,59 +354,53 @@ struct OllamaRequest {
     model: String,
     prompt: String,
     stream: bool,
-    format: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keep_alive: Option<KeepAlive>,
     options: OllamaOptions,
 }
 
-#[derive(Serialize)]
-struct OllamaOptions {
-    temperature: f32,
-    top_p: f32,
+#[derive(Deserialize)]
+struct OllamaResponse {
+    response: String,
 }
 
 #[derive(Deserialize)]
-struct OllamaResponse {
+struct OllamaStreamChunk {
+    #[serde(default)]
     response: String,
+    error: Option<String>,
 }
 
 #[async_trait]
 impl LlmClient for OllamaClient {
     async fn query(&self, prompt: String, _working_dir: &Path) -> Result<String> {
-        log::debug!("Ollama query to model: {}", self.model);
-        log::debug!("Prompt length: {} chars", prompt.len());
-
-        let client = reqwest::Client::new();
-        
-        let request = OllamaRequest {
-            model: self.model.clone(),
-            prompt,
-            stream: false,
-            format: "json".to_string(),
-            options: OllamaOptions {
-                temperature: 0.7,
-                top_p: 0.9,
-            },
-        };
-
-        let response = tokio::time::timeout(
-            std::time::Duration::from_secs(60),
-            client
-                .post(format!("{}/api/generate", self.base_url))
-                .json(&request)
-                .send()
-        )
-        .await
-        .map_err(|_| anyhow!("Ollama query timed out after 60 seconds"))??;
+        self.query_with_options(prompt, &OllamaOptions::default()).await
+    }
 
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow!("Ollama request failed: {}", error_text));
+    async fn query_with_context(
+        &self,
+        prompt: String,
+        _working_dir: &Path,
+        context: &QueryContext,
+    ) -> Result<String> {
+        match self.config.role_options.get(&context.role) {
+            Some(overrides) => self.query_with_options(prompt, overrides).await,
+            None => self.query_with_options(prompt, &OllamaOptions::default()).await,
         }
+    }
 
-        let ollama_response: OllamaResponse = response.json().await
-            .map_err(|e| anyhow!("Failed to parse Ollama response: {}", e))?;
-
-        Ok(ollama_response.response)
+    async fn query_stream(
+        &self,
+        prompt: String,
+        _working_dir: &Path,
+        context: &QueryContext,
+    ) -> Result<TokenStream> {
+        match self.config.role_options.get(&context.role) {
+            Some(overrides) => self.stream_with_options(prompt, overrides).await,
+            None => self.stream_with_options(prompt, &OllamaOptions::default()).await,
+        }
     }
 }
 
@@ -94,10 +411,16 @@ pub async fn check_ollama_status(base_url: &str) -> Result<()> {
         .get(format!("{}/api/tags", base_url))
         .send()
         .await
-        .map_err(|e| anyhow!("Failed to connect to Ollama: {}", e))?;
+        .map_err(|e| Error::LlmHttp {
+            status: None,
+            message: format!("Failed to connect to Ollama: {}", e),
+        })?;
 
     if !response.status().is_success() {
-        return Err(anyhow!("Ollama is not responding correctly"));
+        return Err(Error::LlmHttp {
+            status: Some(response.status().as_u16()),
+            message: "Ollama is not responding correctly".to_string(),
+        });
     }
 
     Ok(())
@@ -123,4 +446,152 @@ pub async fn list_ollama_models(base_url: &str) -> Result<Vec<String>> {
 
     let models_response: ModelsResponse = response.json().await?;
     Ok(models_response.models.into_iter().map(|m| m.name).collect())
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::task::JoinHandle;
+
+    /// A one-shot HTTP server that answers with `head` and `body`
+    ///
+    /// Returns the base URL to give the client and a handle yielding the raw request received.
+    async fn serve(head: &str, body: String) -> (String, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let response = format!("{}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", head, body.len(), body);
+
+        let handle = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let request = read_request(&mut socket).await;
+            socket.write_all(response.as_bytes()).await.unwrap();
+            request
+        });
+        (url, handle)
+    }
+
+    async fn read_request(socket: &mut tokio::net::TcpStream) -> String {
+        let mut request = Vec::new();
+        let mut buf = [0; 4096];
+        loop {
+            let read = socket.read(&mut buf).await.unwrap();
+            request.extend_from_slice(&buf[..read]);
+            let text = String::from_utf8_lossy(&request);
+            if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                let length = head
+                    .lines()
+                    .find_map(|line| line.to_lowercase().strip_prefix("content-length:").map(|n| n.trim().to_string()))
+                    .and_then(|n| n.parse::<usize>().ok())
+                    .unwrap_or(0);
+                if body.len() >= length {
+                    return text.into_owned();
+                }
+            }
+            if read == 0 {
+                return text.into_owned();
+            }
+        }
+    }
+
+    fn stream_body(lines: &[&str]) -> String {
+        lines.iter().map(|line| format!("{}\n", line)).collect()
+    }
+
+    #[tokio::test]
+    async fn query_stream_yields_each_response_piece() {
+        let body = stream_body(&[
+            r#"{"model": "test-model", "response": "{\"dialogue\": ", "done": false}"#,
+            r#"{"model": "test-model", "response": "", "done": false}"#,
+            r#"{"model": "test-model", "response": "\"Hi\"}", "done": false}"#,
+            r#"{"model": "test-model", "response": "", "done": true, "eval_count": 7}"#,
+        ]);
+        let (url, server) = serve("HTTP/1.1 200 OK\r\nContent-Type: application/x-ndjson", body).await;
+        let client = OllamaClient::with_url("test-model", url);
+
+        let stream = client
+            .query_stream("Say hi".to_string(), Path::new("."), &QueryContext::intent("bob"))
+            .await
+            .unwrap();
+        let chunks: Vec<String> = stream.map(|chunk| chunk.unwrap()).collect().await;
+        assert_eq!(chunks, [r#"{"dialogue": "#, r#""Hi"}"#]);
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /api/generate HTTP/1.1"), "{}", request);
+        assert!(request.contains(r#""stream":true"#), "{}", request);
+    }
+
+    #[tokio::test]
+    async fn query_stream_reports_errors_sent_mid_stream() {
+        let body = stream_body(&[
+            r#"{"model": "test-model", "response": "{\"dia", "done": false}"#,
+            r#"{"error": "model ran out of memory"}"#,
+        ]);
+        let (url, _server) = serve("HTTP/1.1 200 OK\r\nContent-Type: application/x-ndjson", body).await;
+        let client = OllamaClient::with_url("test-model", url);
+
+        let stream = client
+            .query_stream("Say hi".to_string(), Path::new("."), &QueryContext::intent("bob"))
+            .await
+            .unwrap();
+        let chunks: Vec<Result<String>> = stream.collect().await;
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].as_ref().unwrap(), r#"{"dia"#);
+        let error = chunks[1].as_ref().unwrap_err().to_string();
+        assert!(error.contains("model ran out of memory"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn query_stream_rejects_malformed_lines() {
+        let (url, _server) = serve("HTTP/1.1 200 OK", stream_body(&["not json"])).await;
+        let client = OllamaClient::with_url("test-model", url);
+
+        let stream = client
+            .query_stream("Say hi".to_string(), Path::new("."), &QueryContext::gm())
+            .await
+            .unwrap();
+        let chunks: Vec<Result<String>> = stream.collect().await;
+        assert_eq!(chunks.len(), 1);
+        assert!(chunks[0].as_ref().unwrap_err().to_string().contains("Ollama stream chunk"));
+    }
+
+    #[test]
+    fn request_body_carries_config_and_overrides() {
+        let config = OllamaClientConfig::new("test-model")
+            .with_seed(42)
+            .with_keep_alive("10m")
+            .with_role_options(LlmRole::Gm, OllamaOptions::new().with_temperature(0.2));
+        let client = OllamaClient::from_config(config);
+
+        let request = client.build_request("Say hi".to_string(), &client.config().role_options[&LlmRole::Gm], false);
+        let body = serde_json::to_value(&request).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "model": "test-model",
+                "prompt": "Say hi",
+                "stream": false,
+                "format": "json",
+                "keep_alive": "10m",
+                "options": {"temperature": 0.2f32, "top_p": 0.9f32, "seed": 42},
+            })
+        );
+    }
+
+    #[test]
+    fn keep_alive_serialises_as_ollama_expects() {
+        let body = |keep_alive: Option<KeepAlive>| {
+            let mut config = OllamaClientConfig::new("test-model").with_format(None);
+            config.keep_alive = keep_alive;
+            let client = OllamaClient::from_config(config);
+            serde_json::to_value(client.build_request(String::new(), &OllamaOptions::default(), false)).unwrap()
+        };
+
+        assert_eq!(body(Some(KeepAlive::forever()))["keep_alive"], -1);
+        assert_eq!(body(Some(KeepAlive::unload()))["keep_alive"], 0);
+        assert_eq!(body(Some(Duration::from_secs(300).into()))["keep_alive"], 300);
+        assert_eq!(body(Some("24h".into()))["keep_alive"], "24h");
+        let unset = body(None);
+        assert!(unset.get("keep_alive").is_none() && unset.get("format").is_none(), "{}", unset);
+    }
+}
@@ -0,0 +1,428 @@
+use async_trait::async_trait;
+use futures::TryStreamExt;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+
+use super::{LlmClient, QueryContext, TokenStream};
+use crate::error::{Error, Result};
+
+/// Separator the prompt builder places between prompt sections
+const SECTION_SEPARATOR: &str = "\n\n---\n\n";
+
+/// Client for servers speaking the OpenAI `/v1/chat/completions` protocol,
+/// such as llama.cpp server, vLLM or LM Studio
+pub struct OpenAiCompatibleClient {
+    model: String,
+    base_url: String,
+    api_key: Option<String>,
+    api_key_header: String,
+    system_prompt: Option<String>,
+    split_system_prompt: bool,
+    json_response: bool,
+    temperature: f32,
+    max_tokens: Option<u32>,
+    timeout: Duration,
+    client: reqwest::Client,
+}
+
+impl OpenAiCompatibleClient {
+    /// Create a client for a server on the default llama.cpp address
+    pub fn new(model: impl Into<String>) -> Self {
+        Self::with_url(model, "http://localhost:8080/v1")
+    }
+
+    /// Create a client for a server at the given base URL (including the `/v1` prefix)
+    pub fn with_url(model: impl Into<String>, base_url: impl Into<String>) -> Self {
+        Self {
+            model: model.into(),
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            api_key: None,
+            api_key_header: "Authorization".to_string(),
+            system_prompt: None,
+            split_system_prompt: false,
+            json_response: true,
+            temperature: 0.7,
+            max_tokens: None,
+            timeout: Duration::from_secs(60),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Send an API key with every request, as `Authorization: Bearer <key>` by default
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Send the API key in a different header, e.g. `api-key` or `x-api-key`
+    ///
+    /// Only the `Authorization` header gets the `Bearer ` prefix; other headers carry the raw key.
+    pub fn with_api_key_header(mut self, header: impl Into<String>) -> Self {
+        self.api_key_header = header.into();
+        self
+    }
+
+    /// Send a fixed system message ahead of every prompt
+    pub fn with_system_prompt(mut self, system_prompt: impl Into<String>) -> Self {
+        self.system_prompt = Some(system_prompt.into());
+        self
+    }
+
+    /// Send the first prompt section (the base instructions) as the system message
+    /// and the remaining sections as the user message
+    pub fn with_split_system_prompt(mut self, split: bool) -> Self {
+        self.split_system_prompt = split;
+        self
+    }
+
+    /// Request `response_format: {"type": "json_object"}` (enabled by default)
+    pub fn with_json_response(mut self, json_response: bool) -> Self {
+        self.json_response = json_response;
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = temperature;
+        self
+    }
+
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Turn a prompt into chat messages according to the system prompt settings
+    fn build_messages(&self, prompt: String) -> Vec<ChatMessage> {
+        let mut messages = Vec::new();
+
+        if let Some(system) = &self.system_prompt {
+            messages.push(ChatMessage::new("system", system.clone()));
+        }
+
+        if self.split_system_prompt {
+            if let Some((system, user)) = prompt.split_once(SECTION_SEPARATOR) {
+                messages.push(ChatMessage::new("system", system.to_string()));
+                messages.push(ChatMessage::new("user", user.to_string()));
+                return messages;
+            }
+        }
+
+        messages.push(ChatMessage::new("user", prompt));
+        messages
+    }
+
+    fn build_request(&self, prompt: String, stream: bool) -> ChatRequest {
+        log::debug!("OpenAI-compatible query to model: {}", self.model);
+        log::debug!("Prompt length: {} chars", prompt.len());
+
+        ChatRequest {
+            model: self.model.clone(),
+            messages: self.build_messages(prompt),
+            stream,
+            temperature: self.temperature,
+            max_tokens: self.max_tokens,
+            response_format: self.json_response.then(|| ResponseFormat {
+                format_type: "json_object".to_string(),
+            }),
+        }
+    }
+
+    /// Send a chat request, failing on error statuses
+    async fn post(&self, request: ChatRequest) -> Result<reqwest::Response> {
+        let mut builder = self
+            .client
+            .post(format!("{}/chat/completions", self.base_url))
+            .json(&request);
+
+        if let Some(api_key) = &self.api_key {
+            let value = if self.api_key_header.eq_ignore_ascii_case("authorization") {
+                format!("Bearer {}", api_key)
+            } else {
+                api_key.clone()
+            };
+            builder = builder.header(self.api_key_header.as_str(), value);
+        }
+
+        let response = builder.send().await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await?;
+            return Err(Error::LlmHttp {
+                status: Some(status.as_u16()),
+                message: format!("Chat completion request failed ({}): {}", status, error_text),
+            });
+        }
+
+        Ok(response)
+    }
+
+    /// Send a chat request and read the reply from the first choice
+    async fn send(&self, request: ChatRequest) -> Result<String> {
+        let response = self.post(request).await?;
+
+        let chat_response: ChatResponse = response.json().await
+            .map_err(|e| Error::Llm(format!("Failed to parse chat completion response: {}", e)))?;
+
+        chat_response
+            .choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.message.content)
+            .ok_or_else(|| Error::Llm("Chat completion response contained no choices".to_string()))
+    }
+}
+
+#[derive(Serialize)]
+struct ChatRequest {
+    model: String,
+    messages: Vec<ChatMessage>,
+    stream: bool,
+    temperature: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<ResponseFormat>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ChatMessage {
+    role: String,
+    content: String,
+}
+
+impl ChatMessage {
+    fn new(role: &str, content: String) -> Self {
+        Self {
+            role: role.to_string(),
+            content,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ResponseFormat {
+    #[serde(rename = "type")]
+    format_type: String,
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    choices: Vec<ChatChoice>,
+}
+
+#[derive(Deserialize)]
+struct ChatChoice {
+    message: ChatMessage,
+}
+
+#[derive(Deserialize)]
+struct ChatStreamChunk {
+    choices: Vec<ChatStreamChoice>,
+}
+
+#[derive(Deserialize)]
+struct ChatStreamChoice {
+    delta: ChatDelta,
+}
+
+#[derive(Deserialize)]
+struct ChatDelta {
+    content: Option<String>,
+}
+
+#[async_trait]
+impl LlmClient for OpenAiCompatibleClient {
+    /// The timeout covers the whole exchange, reading the response body included
+    async fn query(&self, prompt: String, _working_dir: &Path) -> Result<String> {
+        let request = self.build_request(prompt, false);
+
+        tokio::time::timeout(self.timeout, self.send(request))
+            .await
+            .map_err(|_| Error::LlmTimeout { timeout: self.timeout })?
+    }
+
+    /// Streams server-sent events; the timeout applies to the request, then to the wait for each event
+    async fn query_stream(
+        &self,
+        prompt: String,
+        _working_dir: &Path,
+        _context: &QueryContext,
+    ) -> Result<TokenStream> {
+        let response = tokio::time::timeout(self.timeout, self.post(self.build_request(prompt, true)))
+            .await
+            .map_err(|_| Error::LlmTimeout { timeout: self.timeout })??;
+
+        let chunks = super::stream::body_lines(response.bytes_stream(), self.timeout)
+            .try_filter_map(|line| async move {
+                // Skip comments, event names and the final `data: [DONE]`
+                let Some(data) = line.strip_prefix("data:").map(str::trim) else {
+                    return Ok(None);
+                };
+                if data == "[DONE]" {
+                    return Ok(None);
+                }
+
+                let chunk: ChatStreamChunk = serde_json::from_str(data)
+                    .map_err(|e| Error::Llm(format!("Failed to parse chat completion chunk: {}", e)))?;
+                Ok(chunk
+                    .choices
+                    .into_iter()
+                    .next()
+                    .and_then(|choice| choice.delta.content)
+                    .filter(|content| !content.is_empty()))
+            });
+
+        Ok(Box::pin(chunks))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::task::JoinHandle;
+
+    const JSON_OK: &str = "HTTP/1.1 200 OK\r\nContent-Type: application/json";
+
+    /// A one-shot HTTP server that answers with `head`, waits `stall`, then sends `body`
+    ///
+    /// Returns the base URL to give the client and a handle yielding the raw request received.
+    async fn serve(head: &str, body: String, stall: Duration) -> (String, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/v1", listener.local_addr().unwrap());
+        let head = format!("{}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", head, body.len());
+
+        let handle = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let request = read_request(&mut socket).await;
+            socket.write_all(head.as_bytes()).await.unwrap();
+            tokio::time::sleep(stall).await;
+            // The client may have given up by now
+            let _ = socket.write_all(body.as_bytes()).await;
+            request
+        });
+        (url, handle)
+    }
+
+    async fn read_request(socket: &mut tokio::net::TcpStream) -> String {
+        let mut request = Vec::new();
+        let mut buf = [0; 4096];
+        loop {
+            let read = socket.read(&mut buf).await.unwrap();
+            request.extend_from_slice(&buf[..read]);
+            let text = String::from_utf8_lossy(&request);
+            if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                let length = head
+                    .lines()
+                    .find_map(|line| line.to_lowercase().strip_prefix("content-length:").map(|n| n.trim().to_string()))
+                    .and_then(|n| n.parse::<usize>().ok())
+                    .unwrap_or(0);
+                if body.len() >= length {
+                    return text.into_owned();
+                }
+            }
+            if read == 0 {
+                return text.into_owned();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn query_reads_the_first_choice() {
+        let body = r#"{"choices": [{"message": {"role": "assistant", "content": "{\"action\": \"waves\"}"}}]}"#;
+        let (url, server) = serve(JSON_OK, body.to_string(), Duration::ZERO).await;
+        let client = OpenAiCompatibleClient::with_url("test-model", url)
+            .with_api_key("secret")
+            .with_split_system_prompt(true);
+
+        let response = client.query(format!("Be bob{}Say hi", SECTION_SEPARATOR), Path::new(".")).await.unwrap();
+        assert_eq!(response, r#"{"action": "waves"}"#);
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /v1/chat/completions HTTP/1.1"), "{}", request);
+        assert!(request.to_lowercase().contains("authorization: bearer secret"));
+        let body: serde_json::Value = serde_json::from_str(request.split_once("\r\n\r\n").unwrap().1).unwrap();
+        assert_eq!(body["model"], "test-model");
+        assert_eq!(body["stream"], false);
+        assert_eq!(body["response_format"]["type"], "json_object");
+        assert_eq!(body["messages"][0], serde_json::json!({"role": "system", "content": "Be bob"}));
+        assert_eq!(body["messages"][1], serde_json::json!({"role": "user", "content": "Say hi"}));
+    }
+
+    #[tokio::test]
+    async fn query_stream_yields_each_delta() {
+        let body = [
+            ": keep-alive",
+            r#"data: {"choices": [{"delta": {"role": "assistant"}}]}"#,
+            r#"data: {"choices": [{"delta": {"content": "{\"dialogue\": "}}]}"#,
+            r#"data: {"choices": [{"delta": {"content": "\"Hi\"}"}}]}"#,
+            r#"data: {"choices": [{"delta": {"content": ""}}]}"#,
+            "data: [DONE]",
+        ]
+        .map(|line| format!("{}\n\n", line))
+        .concat();
+        let (url, server) = serve("HTTP/1.1 200 OK\r\nContent-Type: text/event-stream", body, Duration::ZERO).await;
+        let client = OpenAiCompatibleClient::with_url("test-model", url);
+
+        let stream = client
+            .query_stream("Say hi".to_string(), Path::new("."), &QueryContext::intent("bob"))
+            .await
+            .unwrap();
+        let chunks: Vec<String> = stream.map(|chunk| chunk.unwrap()).collect().await;
+        assert_eq!(chunks, [r#"{"dialogue": "#, r#""Hi"}"#]);
+
+        let request = server.await.unwrap();
+        assert!(request.contains(r#""stream":true"#), "{}", request);
+    }
+
+    #[tokio::test]
+    async fn query_stream_rejects_malformed_events() {
+        let body = "data: {\"choices\": [{\"delta\": {\"content\": \"Hi\"}}]}\n\ndata: {oops\n\n".to_string();
+        let (url, _server) = serve("HTTP/1.1 200 OK\r\nContent-Type: text/event-stream", body, Duration::ZERO).await;
+        let client = OpenAiCompatibleClient::with_url("test-model", url);
+
+        let stream = client
+            .query_stream("Say hi".to_string(), Path::new("."), &QueryContext::gm())
+            .await
+            .unwrap();
+        let chunks: Vec<Result<String>> = stream.collect().await;
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].as_ref().unwrap(), "Hi");
+        assert!(chunks[1].as_ref().unwrap_err().to_string().contains("chat completion chunk"));
+    }
+
+    #[tokio::test]
+    async fn timeout_covers_reading_the_body() {
+        let body = r#"{"choices": [{"message": {"role": "assistant", "content": "late"}}]}"#;
+        let (url, _server) = serve(JSON_OK, body.to_string(), Duration::from_secs(5)).await;
+        let client = OpenAiCompatibleClient::with_url("test-model", url).with_timeout(Duration::from_millis(200));
+
+        let error = client.query("Say hi".to_string(), Path::new(".")).await.unwrap_err();
+        assert!(matches!(error, Error::LlmTimeout { .. }), "{:?}", error);
+    }
+
+    #[tokio::test]
+    async fn error_status_is_reported() {
+        let head = "HTTP/1.1 503 Service Unavailable";
+        let (url, _server) = serve(head, "model is loading".to_string(), Duration::ZERO).await;
+        let client = OpenAiCompatibleClient::with_url("test-model", url);
+
+        let error = client.query("Say hi".to_string(), Path::new(".")).await.unwrap_err();
+        match error {
+            Error::LlmHttp { status, message } => {
+                assert_eq!(status, Some(503));
+                assert!(message.contains("model is loading"), "{}", message);
+            }
+            other => panic!("expected an HTTP error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use async_trait::async_trait;
+use futures::StreamExt;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Mutex, OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
+
+use super::{LlmClient, QueryContext, TokenStream};
+use crate::error::{Error, Result};
+
+/// Limits how hard a single backend is pushed, however many queries the engine sends at once
+///
+/// Queries beyond `max_concurrent` wait for a slot, and with a minimum interval set, query
+/// starts are spaced out by at least that long. Wrap each backend separately when routing
+/// to several, so each gets its own limits.
+///
+/// ```rust,no_run
+/// use social_npc::llm::{LlmRole, LlmRouter, OllamaClient, RateLimitedClient};
+/// use std::time::Duration;
+///
+/// // One local Ollama handles two queries at a time; the remote GM allows 30 per minute
+/// let local = RateLimitedClient::new(OllamaClient::new("llama3.2:1b"), 2);
+/// let remote = RateLimitedClient::new(OllamaClient::with_url("llama3.1:70b", "http://gpu-box:11434"), 4)
+///     .with_requests_per_minute(30);
+/// let router = LlmRouter::new(local).with_role(LlmRole::Gm, remote);
+/// ```
+pub struct RateLimitedClient {
+    inner: Arc<dyn LlmClient>,
+    permits: Arc<Semaphore>,
+    min_interval: Option<Duration>,
+    next_start: Mutex<Option<Instant>>,
+}
+
+impl RateLimitedClient {
+    /// Allow at most `max_concurrent` queries to `inner` at a time (at least one)
+    pub fn new(inner: impl LlmClient + 'static, max_concurrent: usize) -> Self {
+        Self {
+            inner: Arc::new(inner),
+            permits: Arc::new(Semaphore::new(max_concurrent.max(1))),
+            min_interval: None,
+            next_start: Mutex::new(None),
+        }
+    }
+
+    /// Start queries at least `interval` apart
+    pub fn with_min_interval(mut self, interval: Duration) -> Self {
+        self.min_interval = Some(interval);
+        self
+    }
+
+    /// Start at most `requests` queries per minute, spread evenly
+    pub fn with_requests_per_minute(self, requests: u32) -> Self {
+        self.with_min_interval(Duration::from_secs(60) / requests.max(1))
+    }
+
+    /// Queries that could start right now without waiting for a slot
+    pub fn available_slots(&self) -> usize {
+        self.permits.available_permits()
+    }
+
+    /// Wait for a free slot and, if rate limited, for this query's turn to start
+    async fn acquire(&self) -> Result<OwnedSemaphorePermit> {
+        let permit = self
+            .permits
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|_| Error::Llm("Rate limiter closed".to_string()))?;
+
+        if let Some(interval) = self.min_interval {
+            // Held while waiting, so the next start counts from when this one really happens
+            // rather than when it was due, which a late wake-up could make too soon
+            let mut next_start = self.next_start.lock().await;
+            if let Some(next) = *next_start {
+                tokio::time::sleep_until(next).await;
+            }
+            *next_start = Some(Instant::now() + interval);
+        }
+
+        Ok(permit)
+    }
+}
+
+#[async_trait]
+impl LlmClient for RateLimitedClient {
+    async fn query(&self, prompt: String, working_dir: &Path) -> Result<String> {
+        let _permit = self.acquire().await?;
+        self.inner.query(prompt, working_dir).await
+    }
+
+    async fn query_with_context(
+        &self,
+        prompt: String,
+        working_dir: &Path,
+        context: &QueryContext,
+    ) -> Result<String> {
+        let _permit = self.acquire().await?;
+        self.inner.query_with_context(prompt, working_dir, context).await
+    }
+
+    async fn query_stream(
+        &self,
+        prompt: String,
+        working_dir: &Path,
+        context: &QueryContext,
+    ) -> Result<TokenStream> {
+        let permit = self.acquire().await?;
+        let chunks = self.inner.query_stream(prompt, working_dir, context).await?;
+
+        // The slot stays taken until the stream is finished with
+        Ok(Box::pin(chunks.map(move |chunk| {
+            let _ = &permit;
+            chunk
+        })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    /// Takes `delay` to answer, recording when each query started and the most in flight at once
+    #[derive(Default)]
+    struct Probe {
+        delay: Duration,
+        starts: Mutex<Vec<Instant>>,
+        in_flight: AtomicUsize,
+        max_in_flight: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl LlmClient for Probe {
+        async fn query(&self, _prompt: String, _working_dir: &Path) -> Result<String> {
+            self.starts.lock().unwrap().push(Instant::now());
+            let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(now, Ordering::SeqCst);
+            tokio::time::sleep(self.delay).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            Ok("done".to_string())
+        }
+    }
+
+    async fn query_all(client: &RateLimitedClient, count: usize) {
+        let queries = (0..count).map(|_| client.query(String::new(), Path::new(".")));
+        for result in futures::future::join_all(queries).await {
+            assert_eq!(result.unwrap(), "done");
+        }
+    }
+
+    #[tokio::test]
+    async fn at_most_max_concurrent_queries_run_at_once() {
+        let probe = Arc::new(Probe { delay: Duration::from_millis(30), ..Default::default() });
+        let client = RateLimitedClient::new(probe.clone(), 2);
+
+        query_all(&client, 6).await;
+
+        assert_eq!(probe.max_in_flight.load(Ordering::SeqCst), 2);
+        assert_eq!(client.available_slots(), 2);
+    }
+
+    #[tokio::test]
+    async fn query_starts_are_spaced_by_the_interval() {
+        let probe = Arc::new(Probe::default());
+        let interval = Duration::from_millis(40);
+        let client = RateLimitedClient::new(probe.clone(), 4).with_min_interval(interval);
+
+        query_all(&client, 4).await;
+
+        let mut starts = probe.starts.lock().unwrap().clone();
+        starts.sort();
+        assert_eq!(starts.len(), 4);
+        for pair in starts.windows(2) {
+            // A start is recorded a moment after it was scheduled, so allow a little slack
+            assert!(pair[1] - pair[0] >= interval - Duration::from_millis(1), "{:?}", pair[1] - pair[0]);
+        }
+    }
+
+    #[test]
+    fn requests_per_minute_become_an_interval() {
+        let client = RateLimitedClient::new(Probe::default(), 1).with_requests_per_minute(30);
+        assert_eq!(client.min_interval, Some(Duration::from_secs(2)));
+        assert_eq!(RateLimitedClient::new(Probe::default(), 0).available_slots(), 1);
+    }
+}
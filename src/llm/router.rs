@@ -0,0 +1,156 @@
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use super::{LlmClient, LlmRole, QueryContext, TokenStream};
+use crate::error::Result;
+
+/// Sends each query to a different client depending on its call site and NPC
+///
+/// The most specific route wins: NPC and role, then NPC, then role, then the default.
+/// Queries without context always go to the default client.
+///
+/// ```rust,no_run
+/// use social_npc::{NpcEngine, llm::{LlmRole, LlmRouter, OllamaClient}};
+///
+/// # fn example() -> social_npc::Result<()> {
+/// let router = LlmRouter::new(OllamaClient::new("llama3.2:1b"))
+///     .with_role(LlmRole::Gm, OllamaClient::new("llama3.1:70b"))
+///     .with_npc("mayor", OllamaClient::new("llama3.1:8b"));
+/// let engine = NpcEngine::new("./data", router)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct LlmRouter {
+    default: Arc<dyn LlmClient>,
+    by_role: HashMap<LlmRole, Arc<dyn LlmClient>>,
+    by_npc: HashMap<String, Arc<dyn LlmClient>>,
+    by_npc_role: HashMap<(String, LlmRole), Arc<dyn LlmClient>>,
+}
+
+impl LlmRouter {
+    /// Create a router sending everything to `default` until routes are added
+    pub fn new(default: impl LlmClient + 'static) -> Self {
+        Self {
+            default: Arc::new(default),
+            by_role: HashMap::new(),
+            by_npc: HashMap::new(),
+            by_npc_role: HashMap::new(),
+        }
+    }
+
+    /// Route every query from a call site to `client`
+    pub fn with_role(mut self, role: LlmRole, client: impl LlmClient + 'static) -> Self {
+        self.by_role.insert(role, Arc::new(client));
+        self
+    }
+
+    /// Route every query built for an NPC to `client`
+    pub fn with_npc(mut self, npc: impl Into<String>, client: impl LlmClient + 'static) -> Self {
+        self.by_npc.insert(npc.into(), Arc::new(client));
+        self
+    }
+
+    /// Route queries from one call site for one NPC to `client`
+    pub fn with_npc_role(
+        mut self,
+        npc: impl Into<String>,
+        role: LlmRole,
+        client: impl LlmClient + 'static,
+    ) -> Self {
+        self.by_npc_role.insert((npc.into(), role), Arc::new(client));
+        self
+    }
+
+    /// The client a query with this context will be sent to
+    pub fn route(&self, context: &QueryContext) -> &Arc<dyn LlmClient> {
+        if let Some(npc) = &context.npc {
+            if let Some(client) = self.by_npc_role.get(&(npc.clone(), context.role)) {
+                return client;
+            }
+            if let Some(client) = self.by_npc.get(npc) {
+                return client;
+            }
+        }
+
+        self.by_role.get(&context.role).unwrap_or(&self.default)
+    }
+}
+
+#[async_trait]
+impl LlmClient for LlmRouter {
+    async fn query(&self, prompt: String, working_dir: &Path) -> Result<String> {
+        self.default.query(prompt, working_dir).await
+    }
+
+    async fn query_with_context(
+        &self,
+        prompt: String,
+        working_dir: &Path,
+        context: &QueryContext,
+    ) -> Result<String> {
+        self.route(context)
+            .query_with_context(prompt, working_dir, context)
+            .await
+    }
+
+    async fn query_stream(
+        &self,
+        prompt: String,
+        working_dir: &Path,
+        context: &QueryContext,
+    ) -> Result<TokenStream> {
+        self.route(context)
+            .query_stream(prompt, working_dir, context)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::MockLlmClient;
+    use futures::StreamExt;
+
+    fn router() -> LlmRouter {
+        let client = |name: &str| MockLlmClient::new().with_default(name);
+        LlmRouter::new(client("default"))
+            .with_role(LlmRole::Gm, client("gm"))
+            .with_role(LlmRole::Intent, client("intent"))
+            .with_npc("mayor", client("mayor"))
+            .with_npc_role("mayor", LlmRole::MemoryUpdate, client("mayor memories"))
+    }
+
+    async fn answer(router: &LlmRouter, context: QueryContext) -> String {
+        router.query_with_context(String::new(), Path::new("."), &context).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn most_specific_route_wins() {
+        let router = router();
+
+        assert_eq!(answer(&router, QueryContext::memory_update("mayor")).await, "mayor memories");
+        assert_eq!(answer(&router, QueryContext::intent("mayor")).await, "mayor");
+        assert_eq!(answer(&router, QueryContext::intent("baker")).await, "intent");
+        assert_eq!(answer(&router, QueryContext::gm()).await, "gm");
+        assert_eq!(answer(&router, QueryContext::memory_update("baker")).await, "default");
+    }
+
+    #[tokio::test]
+    async fn queries_without_context_go_to_the_default() {
+        let router = router();
+        assert_eq!(router.query(String::new(), Path::new(".")).await.unwrap(), "default");
+    }
+
+    #[tokio::test]
+    async fn streams_follow_the_same_routes() {
+        let router = router();
+        let stream = router
+            .query_stream(String::new(), Path::new("."), &QueryContext::intent("mayor"))
+            .await
+            .unwrap();
+        let chunks: Vec<String> = stream.map(|chunk| chunk.unwrap()).collect().await;
+        assert_eq!(chunks, ["mayor"]);
+    }
+}
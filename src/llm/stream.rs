@@ -0,0 +1,96 @@
+use futures::{Stream, StreamExt};
+use std::pin::Pin;
+use std::time::Duration;
+
+use crate::error::{Error, Result};
+
+/// Split a streamed HTTP body into trimmed, non-empty lines
+///
+/// `idle_timeout` bounds the wait for each chunk rather than the whole response, so long
+/// generations aren't cut off as long as the server keeps sending.
+pub(crate) fn body_lines<S, B>(body: S, idle_timeout: Duration) -> impl Stream<Item = Result<String>> + Send
+where
+    S: Stream<Item = reqwest::Result<B>> + Send + 'static,
+    B: AsRef<[u8]>,
+{
+    let state = LineState {
+        body: Box::pin(body),
+        buffer: Vec::new(),
+        done: false,
+    };
+
+    futures::stream::unfold(state, move |mut state| async move {
+        loop {
+            // Split on bytes so multi-byte characters spanning chunks stay intact
+            if let Some(end) = state.buffer.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = state.buffer.drain(..=end).collect();
+                let line = String::from_utf8_lossy(&line).trim().to_string();
+                if !line.is_empty() {
+                    return Some((Ok(line), state));
+                }
+                continue;
+            }
+
+            if state.done {
+                let rest = std::mem::take(&mut state.buffer);
+                let line = String::from_utf8_lossy(&rest).trim().to_string();
+                return (!line.is_empty()).then_some((Ok(line), state));
+            }
+
+            match tokio::time::timeout(idle_timeout, state.body.next()).await {
+                Ok(Some(Ok(chunk))) => state.buffer.extend_from_slice(chunk.as_ref()),
+                Ok(None) => state.done = true,
+                Ok(Some(Err(e))) => {
+                    state.done = true;
+                    state.buffer.clear();
+                    return Some((Err(e.into()), state));
+                }
+                Err(_) => {
+                    state.done = true;
+                    state.buffer.clear();
+                    return Some((Err(Error::LlmTimeout { timeout: idle_timeout }), state));
+                }
+            }
+        }
+    })
+}
+
+struct LineState<S> {
+    body: Pin<Box<S>>,
+    buffer: Vec<u8>,
+    done: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn lines(chunks: &[&[u8]]) -> Vec<String> {
+        let chunks: Vec<_> = chunks.iter().map(|chunk| Ok::<_, reqwest::Error>(chunk.to_vec())).collect();
+        let body = futures::stream::iter(chunks);
+        body_lines(body, Duration::from_secs(1)).map(|line| line.unwrap()).collect().await
+    }
+
+    #[tokio::test]
+    async fn lines_are_split_across_chunks() {
+        let lines = lines(&[b"{\"a\": 1}\n{\"b\"", b": 2}\n\n  \n", b"{\"c\": 3}"]).await;
+        assert_eq!(lines, [r#"{"a": 1}"#, r#"{"b": 2}"#, r#"{"c": 3}"#]);
+    }
+
+    #[tokio::test]
+    async fn characters_split_between_chunks_stay_intact() {
+        let text = "caf\u{e9}\n".as_bytes();
+        let lines = lines(&[&text[..4], &text[4..]]).await;
+        assert_eq!(lines, ["caf\u{e9}"]);
+    }
+
+    #[tokio::test]
+    async fn stalled_body_times_out() {
+        let body = futures::stream::once(async { Ok::<_, reqwest::Error>(b"partial".to_vec()) })
+            .chain(futures::stream::pending());
+        let results: Vec<_> = body_lines(body, Duration::from_millis(50)).collect().await;
+
+        assert_eq!(results.len(), 1);
+        assert!(matches!(results[0], Err(Error::LlmTimeout { .. })), "{:?}", results[0]);
+    }
+}
@@ -30,7 +30,7 @@ impl MemorySystem {
     pub fn get_or_create_relationship(&mut self, npc_name: impl Into<String>) -> &mut RelationshipMemory {
         self.relationships
             .entry(npc_name.into())
-            .or_insert_with(RelationshipMemory::new)
+            .or_default()
     }
 
     /// Updates immediate context for self
@@ -144,6 +144,8 @@ impl Default for RelationshipMemory {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Memory {
     pub event: String,
+    /// When it happened in game time, see `WorldClock::timestamp`
+    #[serde(default)]
     pub timestamp: DateTime<Utc>,
     pub emotional_impact: String,
     pub importance: f32,  // 0.0 to 1.0
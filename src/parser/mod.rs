@@ -0,0 +1,108 @@
+mod repair;
+mod stream;
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::error::{Error, Result};
+
+pub use repair::JsonRepair;
+pub use stream::{FieldDelta, JsonFieldStream};
+
+/// Extract and parse JSON from LLM responses, handling common formatting issues
+pub fn extract_json<T: DeserializeOwned>(response: &str) -> Result<T> {
+    extract_json_with_repairs(response).map(|(value, _)| value)
+}
+
+/// Extract and parse JSON from LLM responses, reporting which repairs were needed
+///
+/// Well-formed JSON is parsed as-is. Otherwise the response is repaired: comments,
+/// trailing commas, single quotes, bare keys, Python literals and truncated output are
+/// fixed up, and if the response contains several JSON objects the first one matching
+/// `T` is used. `"null"` / `"None"` strings are treated as null whenever `T` accepts that.
+pub fn extract_json_with_repairs<T: DeserializeOwned>(response: &str) -> Result<(T, Vec<JsonRepair>)> {
+    let cleaned = strip_code_fence(response);
+
+    // Fast path: the content between the outermost braces is already valid JSON
+    if let (Some(start), Some(end)) = (cleaned.find('{'), cleaned.rfind('}')) {
+        if start < end {
+            if let Ok(value) = serde_json::from_str::<Value>(&cleaned[start..=end]) {
+                if let Ok(result) = deserialize(value) {
+                    return Ok(result);
+                }
+            }
+        }
+    }
+
+    let candidates = repair::repair_candidates(cleaned);
+    if candidates.is_empty() {
+        return Err(Error::NoJson { response: response.to_string() });
+    }
+
+    let mut first_error = None;
+    for (index, candidate) in candidates.iter().enumerate() {
+        let error = match serde_json::from_str::<Value>(&candidate.json) {
+            Ok(value) => {
+                // Log the parsed JSON for debugging
+                log::debug!("Parsed JSON from LLM: {}", serde_json::to_string_pretty(&value)?);
+
+                match deserialize::<T>(value) {
+                    Ok((result, mut repairs)) => {
+                        repairs.splice(0..0, candidate.repairs.iter().copied());
+                        if index > 0 {
+                            repairs.push(JsonRepair::SkippedObjects);
+                        }
+                        if !repairs.is_empty() {
+                            log::debug!("Repaired LLM JSON: {:?}", repairs);
+                        }
+                        return Ok((result, repairs));
+                    }
+                    Err(source) => Error::SchemaMismatch {
+                        type_name: std::any::type_name::<T>(),
+                        source,
+                        response: response.to_string(),
+                    },
+                }
+            }
+            Err(source) => Error::ParseJson {
+                source,
+                response: response.to_string(),
+            },
+        };
+
+        // Report the first object's problem, it's the one the model most likely meant
+        first_error.get_or_insert(error);
+    }
+
+    Err(first_error.unwrap_or_else(|| Error::NoJson { response: response.to_string() }))
+}
+
+/// Deserialize a value, treating "null"/"None" strings as null if that's what makes it fit
+fn deserialize<T: DeserializeOwned>(value: Value) -> serde_json::Result<(T, Vec<JsonRepair>)> {
+    let mut normalized = value.clone();
+    if repair::normalize_null_strings(&mut normalized) {
+        if let Ok(result) = serde_json::from_value::<T>(normalized) {
+            return Ok((result, vec![JsonRepair::NullStrings]));
+        }
+    }
+
+    let result = serde_json::from_value::<T>(value)?;
+    Ok((result, Vec::new()))
+}
+
+/// Remove a markdown code fence around the response, if there is one
+fn strip_code_fence(response: &str) -> &str {
+    let start = if let Some(pos) = response.find("```json") {
+        pos + 7 // 7 = len("```json")
+    } else if let Some(pos) = response.find("```") {
+        pos + 3
+    } else {
+        return response;
+    };
+
+    // The closing fence may be missing if the output was truncated
+    match response[start..].rfind("```") {
+        Some(end) => response[start..start + end].trim(),
+        None => response[start..].trim(),
+    }
+}
@@ -0,0 +1,472 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+/// A fix applied to LLM output so that it could be parsed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JsonRepair {
+    /// `// ...`, `/* ... */` or `# ...` comments were removed
+    RemovedComments,
+    /// Commas before a closing `}` or `]` were removed
+    RemovedTrailingCommas,
+    /// Single-quoted strings were converted to double quotes
+    ConvertedSingleQuotes,
+    /// Bare object keys were quoted
+    QuotedKeys,
+    /// Python literals (`None`, `True`, `False`) were converted to JSON
+    ConvertedPythonLiterals,
+    /// Raw newlines or tabs inside strings were escaped
+    EscapedControlCharacters,
+    /// `"null"` or `"None"` strings were treated as null
+    NullStrings,
+    /// Output that stopped mid-object was closed off
+    ClosedTruncatedOutput,
+    /// Earlier JSON objects in the response did not match the target type and were skipped
+    SkippedObjects,
+}
+
+/// A JSON object found in a response, with the repairs needed to make it parse
+pub(crate) struct Candidate {
+    pub json: String,
+    pub repairs: Vec<JsonRepair>,
+}
+
+/// Split text into the top-level JSON objects it contains, repairing each one
+///
+/// Objects that run to the end of the text without closing are treated as truncated.
+pub(crate) fn repair_candidates(text: &str) -> Vec<Candidate> {
+    let mut candidates = Vec::new();
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((_, c)) = chars.next() {
+        if c == '{' {
+            candidates.push(repair_object(&mut chars));
+        }
+    }
+
+    candidates
+}
+
+/// Repair one object, starting just after its opening brace
+fn repair_object(chars: &mut Peekable<CharIndices>) -> Candidate {
+    let mut out = String::from("{");
+    let mut repairs = Vec::new();
+    let mut stack = vec!['}'];
+    let mut in_string: Option<char> = None;
+
+    while let Some((_, c)) = chars.next() {
+        if let Some(quote) = in_string {
+            match c {
+                '\\' => match chars.next() {
+                    // \' is not a JSON escape, a plain quote is fine inside double quotes
+                    Some((_, '\'')) => out.push('\''),
+                    Some((_, escaped)) => {
+                        out.push('\\');
+                        out.push(escaped);
+                    }
+                    None => {}
+                },
+                c if c == quote => {
+                    out.push('"');
+                    in_string = None;
+                }
+                '"' => out.push_str("\\\""),
+                '\n' => {
+                    out.push_str("\\n");
+                    add(&mut repairs, JsonRepair::EscapedControlCharacters);
+                }
+                '\r' => {
+                    out.push_str("\\r");
+                    add(&mut repairs, JsonRepair::EscapedControlCharacters);
+                }
+                '\t' => {
+                    out.push_str("\\t");
+                    add(&mut repairs, JsonRepair::EscapedControlCharacters);
+                }
+                c => out.push(c),
+            }
+            continue;
+        }
+
+        match c {
+            '"' => {
+                in_string = Some('"');
+                out.push('"');
+            }
+            '\'' => {
+                in_string = Some('\'');
+                out.push('"');
+                add(&mut repairs, JsonRepair::ConvertedSingleQuotes);
+            }
+            '/' if matches!(chars.peek(), Some((_, '/'))) => {
+                skip_line(chars);
+                add(&mut repairs, JsonRepair::RemovedComments);
+            }
+            '/' if matches!(chars.peek(), Some((_, '*'))) => {
+                chars.next();
+                let mut previous = ' ';
+                for (_, c) in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+                add(&mut repairs, JsonRepair::RemovedComments);
+            }
+            '#' => {
+                skip_line(chars);
+                add(&mut repairs, JsonRepair::RemovedComments);
+            }
+            '{' => {
+                stack.push('}');
+                out.push(c);
+            }
+            '[' => {
+                stack.push(']');
+                out.push(c);
+            }
+            '}' | ']' => {
+                if remove_trailing_comma(&mut out) {
+                    add(&mut repairs, JsonRepair::RemovedTrailingCommas);
+                }
+
+                // Close anything left open inside, e.g. an array missing its `]`
+                if stack.contains(&c) {
+                    while let Some(closer) = stack.pop() {
+                        out.push(closer);
+                        if closer == c {
+                            break;
+                        }
+                    }
+                }
+
+                if stack.is_empty() {
+                    return Candidate { json: out, repairs };
+                }
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut word = String::from(c);
+                while let Some(&(_, next)) = chars.peek() {
+                    if next.is_alphanumeric() || next == '_' || next == '-' {
+                        word.push(next);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+
+                if next_non_whitespace(chars) == Some(':') {
+                    out.push_str(&format!("\"{}\"", word));
+                    add(&mut repairs, JsonRepair::QuotedKeys);
+                } else {
+                    match word.as_str() {
+                        "null" | "true" | "false" => out.push_str(&word),
+                        "None" | "none" | "NULL" | "Null" => {
+                            out.push_str("null");
+                            add(&mut repairs, JsonRepair::ConvertedPythonLiterals);
+                        }
+                        "True" | "TRUE" => {
+                            out.push_str("true");
+                            add(&mut repairs, JsonRepair::ConvertedPythonLiterals);
+                        }
+                        "False" | "FALSE" => {
+                            out.push_str("false");
+                            add(&mut repairs, JsonRepair::ConvertedPythonLiterals);
+                        }
+                        // Not something we know how to fix, leave it for the parser to report
+                        _ => out.push_str(&word),
+                    }
+                }
+            }
+            c => out.push(c),
+        }
+    }
+
+    // Ran out of text before the object closed
+    if in_string.is_some() {
+        out.push('"');
+    }
+    add(&mut repairs, JsonRepair::ClosedTruncatedOutput);
+
+    Candidate {
+        json: close_truncated(out, &stack),
+        repairs,
+    }
+}
+
+/// Close a truncated object, trying progressively more invasive fixes until it parses
+fn close_truncated(mut out: String, stack: &[char]) -> String {
+    let closers: String = stack.iter().rev().collect();
+
+    let trimmed_len = out.trim_end().len();
+    out.truncate(trimmed_len);
+    remove_trailing_comma(&mut out);
+
+    let attempts = [
+        // Cut off between values: {"a": 1, "b": 2
+        format!("{}{}", out, closers),
+        // Cut off after a key: {"a": 1, "b":  or  {"a": 1, "b"
+        format!("{}null{}", out, closers),
+        format!("{}:null{}", out, closers),
+    ];
+
+    for attempt in &attempts {
+        if serde_json::from_str::<Value>(attempt).is_ok() {
+            return attempt.clone();
+        }
+    }
+
+    // Cut off mid-value: drop the incomplete member after the last comma
+    if let Some(comma) = out.rfind(',') {
+        let mut shortened = out[..comma].to_string();
+        // The member may have opened containers of its own; only close what's still open at the comma
+        let depth = open_containers(&shortened);
+        if depth.len() <= stack.len() {
+            let closers: String = depth.iter().rev().collect();
+            shortened.push_str(&closers);
+            if serde_json::from_str::<Value>(&shortened).is_ok() {
+                return shortened;
+            }
+        }
+    }
+
+    attempts[0].clone()
+}
+
+/// The closers needed for containers still open in already-repaired JSON text
+fn open_containers(json: &str) -> Vec<char> {
+    let mut stack = Vec::new();
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for c in json.chars() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' => stack.push('}'),
+            '[' => stack.push(']'),
+            '}' | ']' => {
+                stack.pop();
+            }
+            _ => {}
+        }
+    }
+
+    stack
+}
+
+/// Replace `"null"` / `"None"` string values with real nulls, returning whether anything changed
+pub(crate) fn normalize_null_strings(value: &mut Value) -> bool {
+    match value {
+        Value::String(s) if matches!(s.as_str(), "null" | "None" | "none" | "NULL") => {
+            *value = Value::Null;
+            true
+        }
+        // Visit every element, not just up to the first change
+        Value::Array(items) => {
+            let mut changed = false;
+            for item in items {
+                changed |= normalize_null_strings(item);
+            }
+            changed
+        }
+        Value::Object(map) => {
+            let mut changed = false;
+            for item in map.values_mut() {
+                changed |= normalize_null_strings(item);
+            }
+            changed
+        }
+        _ => false,
+    }
+}
+
+fn add(repairs: &mut Vec<JsonRepair>, repair: JsonRepair) {
+    if !repairs.contains(&repair) {
+        repairs.push(repair);
+    }
+}
+
+fn skip_line(chars: &mut Peekable<CharIndices>) {
+    while let Some(&(_, c)) = chars.peek() {
+        if c == '\n' {
+            break;
+        }
+        chars.next();
+    }
+}
+
+/// Peek past whitespace to the next meaningful character without consuming anything else
+fn next_non_whitespace(chars: &mut Peekable<CharIndices>) -> Option<char> {
+    while let Some(&(_, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else {
+            return Some(c);
+        }
+    }
+    None
+}
+
+/// Remove a comma left dangling at the end of the output, returning whether one was removed
+fn remove_trailing_comma(out: &mut String) -> bool {
+    let trimmed = out.trim_end();
+    if trimmed.ends_with(',') {
+        let len = trimmed.len() - 1;
+        out.truncate(len);
+        true
+    } else {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::extract_json_with_repairs;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(Debug, Deserialize)]
+    struct Reply {
+        name: String,
+        dialogue: Option<String>,
+    }
+
+    fn repaired(text: &str) -> (Value, Vec<JsonRepair>) {
+        extract_json_with_repairs::<Value>(text).unwrap()
+    }
+
+    #[test]
+    fn valid_json_needs_no_repairs() {
+        let (value, repairs) = repaired(r#"Sure! {"name": "bob", "tags": ["a", "b"]} Hope that helps."#);
+        assert_eq!(value, json!({"name": "bob", "tags": ["a", "b"]}));
+        assert!(repairs.is_empty());
+    }
+
+    #[test]
+    fn removes_comments() {
+        let text = "{\n  // who\n  \"name\": \"bob\", /* inline */\n  # python style\n  \"age\": 3\n}";
+        let (value, repairs) = repaired(text);
+        assert_eq!(value, json!({"name": "bob", "age": 3}));
+        assert_eq!(repairs, [JsonRepair::RemovedComments]);
+    }
+
+    #[test]
+    fn removes_trailing_commas() {
+        let (value, repairs) = repaired(r#"{"tags": ["a", "b",], "name": "bob",}"#);
+        assert_eq!(value, json!({"tags": ["a", "b"], "name": "bob"}));
+        assert_eq!(repairs, [JsonRepair::RemovedTrailingCommas]);
+    }
+
+    #[test]
+    fn converts_single_quotes() {
+        let (value, repairs) = repaired(r#"{'name': 'bob', 'line': 'it\'s "fine"'}"#);
+        assert_eq!(value, json!({"name": "bob", "line": "it's \"fine\""}));
+        assert_eq!(repairs, [JsonRepair::ConvertedSingleQuotes]);
+    }
+
+    #[test]
+    fn quotes_bare_keys() {
+        let (value, repairs) = repaired(r#"{name: "bob", next_prompt : null}"#);
+        assert_eq!(value, json!({"name": "bob", "next_prompt": null}));
+        assert_eq!(repairs, [JsonRepair::QuotedKeys]);
+    }
+
+    #[test]
+    fn converts_python_literals() {
+        let (value, repairs) = repaired(r#"{"dialogue": None, "awake": True, "asleep": False}"#);
+        assert_eq!(value, json!({"dialogue": null, "awake": true, "asleep": false}));
+        assert_eq!(repairs, [JsonRepair::ConvertedPythonLiterals]);
+    }
+
+    #[test]
+    fn escapes_control_characters() {
+        let (value, repairs) = repaired("{\"dialogue\": \"Hello\n\tthere\r\"}");
+        assert_eq!(value, json!({"dialogue": "Hello\n\tthere\r"}));
+        assert_eq!(repairs, [JsonRepair::EscapedControlCharacters]);
+    }
+
+    #[test]
+    fn treats_null_strings_as_null() {
+        let (reply, repairs) = extract_json_with_repairs::<Reply>(r#"{"name": "bob", "dialogue": "None"}"#).unwrap();
+        assert_eq!(reply.name, "bob");
+        assert_eq!(reply.dialogue, None);
+        assert_eq!(repairs, [JsonRepair::NullStrings]);
+    }
+
+    #[test]
+    fn closes_truncated_string() {
+        let (value, repairs) = repaired(r#"{"name": "bob", "dialogue": "Good morn"#);
+        assert_eq!(value, json!({"name": "bob", "dialogue": "Good morn"}));
+        assert_eq!(repairs, [JsonRepair::ClosedTruncatedOutput]);
+    }
+
+    #[test]
+    fn closes_truncated_array() {
+        let (value, repairs) = repaired(r#"{"name": "bob", "tags": ["a", "b","#);
+        assert_eq!(value, json!({"name": "bob", "tags": ["a", "b"]}));
+        assert_eq!(repairs, [JsonRepair::ClosedTruncatedOutput]);
+
+        let (value, _) = repaired(r#"{"rows": [[1, 2], [3"#);
+        assert_eq!(value, json!({"rows": [[1, 2], [3]]}));
+    }
+
+    #[test]
+    fn closes_output_cut_after_a_key() {
+        let (value, _) = repaired(r#"{"name": "bob", "dialogue":"#);
+        assert_eq!(value, json!({"name": "bob", "dialogue": null}));
+
+        let (value, _) = repaired(r#"{"name": "bob", "dialogue""#);
+        assert_eq!(value, json!({"name": "bob", "dialogue": null}));
+    }
+
+    #[test]
+    fn drops_member_cut_mid_value() {
+        let (value, repairs) = repaired(r#"{"name": "bob", "awake": tr"#);
+        assert_eq!(value, json!({"name": "bob"}));
+        assert_eq!(repairs, [JsonRepair::ClosedTruncatedOutput]);
+    }
+
+    #[test]
+    fn splits_concatenated_objects() {
+        let candidates = repair_candidates(r#"{"a": 1}{"b": {"c": 2}} and {"d": 3}"#);
+        let objects: Vec<&str> = candidates.iter().map(|c| c.json.as_str()).collect();
+        assert_eq!(objects, [r#"{"a": 1}"#, r#"{"b": {"c": 2}}"#, r#"{"d": 3}"#]);
+        assert!(candidates.iter().all(|c| c.repairs.is_empty()));
+    }
+
+    #[test]
+    fn skips_objects_not_matching_the_target() {
+        let text = r#"{"thinking": "hmm"} {"name": "bob", "dialogue": "Hi"}"#;
+        let (reply, repairs) = extract_json_with_repairs::<Reply>(text).unwrap();
+        assert_eq!(reply.name, "bob");
+        assert_eq!(reply.dialogue.as_deref(), Some("Hi"));
+        assert_eq!(repairs, [JsonRepair::SkippedObjects]);
+    }
+
+    #[test]
+    fn reports_every_repair_once() {
+        let (value, repairs) = repaired("{name: 'bob', // him\n 'tags': ['a', 'b',], }");
+        assert_eq!(value, json!({"name": "bob", "tags": ["a", "b"]}));
+        assert_eq!(
+            repairs,
+            [
+                JsonRepair::QuotedKeys,
+                JsonRepair::ConvertedSingleQuotes,
+                JsonRepair::RemovedComments,
+                JsonRepair::RemovedTrailingCommas,
+            ]
+        );
+    }
+}
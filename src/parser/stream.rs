@@ -0,0 +1,459 @@
+/// Newly generated text of a string field in streamed JSON
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldDelta {
+    /// Where the field is, e.g. `["details", "alice", "dialogue"]`; array elements appear as their index
+    pub path: Vec<String>,
+    /// Text decoded since the previous delta for this field
+    pub text: String,
+    /// Whether the string is complete
+    pub done: bool,
+}
+
+/// Pulls string fields out of a JSON object while it is still being generated
+///
+/// Feed it response chunks as they arrive and it returns the decoded text of every string
+/// whose path matches the pattern, so dialogue can be shown before the JSON is complete.
+/// Anything before the first `{` (preambles, code fences) is skipped.
+///
+/// ```
+/// use social_npc::parser::JsonFieldStream;
+///
+/// let mut stream = JsonFieldStream::intent_dialogue();
+/// let mut spoken = String::new();
+/// for chunk in [r#"{"npc": "bob", "dialogue": "Morn"#, r#"ing, Alice!"}"#] {
+///     for delta in stream.push(chunk) {
+///         spoken.push_str(&delta.text);
+///     }
+/// }
+/// assert_eq!(spoken, "Morning, Alice!");
+/// ```
+pub struct JsonFieldStream {
+    pattern: Vec<String>,
+    stack: Vec<Frame>,
+    state: State,
+    decoder: StringDecoder,
+    started: bool,
+    finished: bool,
+}
+
+enum Frame {
+    Object { key: Option<String> },
+    Array { index: usize },
+}
+
+enum State {
+    ExpectKey,
+    InKey(String),
+    ExpectColon,
+    ExpectValue,
+    /// Inside a string value; holds its path and pending text if it matches the pattern
+    InString(Option<(Vec<String>, String)>),
+    InScalar,
+    AfterValue,
+}
+
+impl JsonFieldStream {
+    /// Watch strings at `pattern`, where `*` matches any object key or array element
+    pub fn new<S: AsRef<str>>(pattern: &[S]) -> Self {
+        Self {
+            pattern: pattern.iter().map(|s| s.as_ref().to_string()).collect(),
+            stack: Vec::new(),
+            state: State::ExpectValue,
+            decoder: StringDecoder::default(),
+            started: false,
+            finished: false,
+        }
+    }
+
+    /// The `dialogue` of an `Intent` or `DialogueReply`
+    pub fn intent_dialogue() -> Self {
+        Self::new(&["dialogue"])
+    }
+
+    /// Each participant's `dialogue` in a `TranscriptEntry`
+    pub fn transcript_dialogue() -> Self {
+        Self::new(&["details", "*", "dialogue"])
+    }
+
+    /// Each participant's `dialogue` in every transcript entry of a `GmResponse`
+    pub fn gm_dialogue() -> Self {
+        Self::new(&["contracts", "*", "transcript_entry", "details", "*", "dialogue"])
+    }
+
+    /// Whether the top-level object has been closed
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Feed the next chunk of the response, returning text decoded for matching fields
+    pub fn push(&mut self, chunk: &str) -> Vec<FieldDelta> {
+        let mut deltas = Vec::new();
+
+        for c in chunk.chars() {
+            if self.finished {
+                break;
+            }
+            if !self.started {
+                if c != '{' {
+                    continue;
+                }
+                self.started = true;
+            }
+            self.step(c, &mut deltas);
+        }
+
+        // Hand out whatever has been decoded of a string that is still open
+        if let State::InString(Some((path, pending))) = &mut self.state {
+            if !pending.is_empty() {
+                deltas.push(FieldDelta {
+                    path: path.clone(),
+                    text: std::mem::take(pending),
+                    done: false,
+                });
+            }
+        }
+
+        deltas
+    }
+
+    fn step(&mut self, c: char, deltas: &mut Vec<FieldDelta>) {
+        match &mut self.state {
+            State::InKey(key) => match self.decoder.feed(c) {
+                Decoded::Char(decoded) => key.push(decoded),
+                Decoded::End => {
+                    let key = std::mem::take(key);
+                    if let Some(Frame::Object { key: current }) = self.stack.last_mut() {
+                        *current = Some(key);
+                    }
+                    self.state = State::ExpectColon;
+                }
+                Decoded::Nothing => {}
+            },
+            State::InString(matched) => match self.decoder.feed(c) {
+                Decoded::Char(decoded) => {
+                    if let Some((_, pending)) = matched {
+                        pending.push(decoded);
+                    }
+                }
+                Decoded::End => {
+                    if let Some((path, pending)) = matched.take() {
+                        deltas.push(FieldDelta {
+                            path,
+                            text: pending,
+                            done: true,
+                        });
+                    }
+                    self.state = State::AfterValue;
+                }
+                Decoded::Nothing => {}
+            },
+            State::ExpectKey => match c {
+                '"' => {
+                    self.decoder = StringDecoder::default();
+                    self.state = State::InKey(String::new());
+                }
+                '}' => self.close(),
+                _ => {}
+            },
+            State::ExpectColon => {
+                if c == ':' {
+                    self.state = State::ExpectValue;
+                }
+            }
+            State::ExpectValue => match c {
+                '"' => {
+                    let path = self.path();
+                    let matched = self.matches(&path).then(|| (path, String::new()));
+                    self.decoder = StringDecoder::default();
+                    self.state = State::InString(matched);
+                }
+                '{' => {
+                    self.stack.push(Frame::Object { key: None });
+                    self.state = State::ExpectKey;
+                }
+                '[' => {
+                    self.stack.push(Frame::Array { index: 0 });
+                    self.state = State::ExpectValue;
+                }
+                // Empty array
+                ']' => self.close(),
+                c if c.is_whitespace() => {}
+                _ => self.state = State::InScalar,
+            },
+            State::InScalar => match c {
+                ',' | '}' | ']' => {
+                    self.state = State::AfterValue;
+                    self.step(c, deltas);
+                }
+                c if c.is_whitespace() => self.state = State::AfterValue,
+                _ => {}
+            },
+            State::AfterValue => match c {
+                ',' => match self.stack.last_mut() {
+                    Some(Frame::Object { key }) => {
+                        *key = None;
+                        self.state = State::ExpectKey;
+                    }
+                    Some(Frame::Array { index }) => {
+                        *index += 1;
+                        self.state = State::ExpectValue;
+                    }
+                    None => {}
+                },
+                '}' | ']' => self.close(),
+                _ => {}
+            },
+        }
+    }
+
+    fn close(&mut self) {
+        self.stack.pop();
+        self.state = State::AfterValue;
+        if self.stack.is_empty() {
+            self.finished = true;
+        }
+    }
+
+    /// Path of the value about to be read
+    fn path(&self) -> Vec<String> {
+        self.stack
+            .iter()
+            .map(|frame| match frame {
+                Frame::Object { key } => key.clone().unwrap_or_default(),
+                Frame::Array { index } => index.to_string(),
+            })
+            .collect()
+    }
+
+    fn matches(&self, path: &[String]) -> bool {
+        path.len() == self.pattern.len()
+            && self.pattern.iter().zip(path).all(|(pattern, part)| pattern == "*" || pattern == part)
+    }
+}
+
+enum Decoded {
+    Char(char),
+    End,
+    Nothing,
+}
+
+/// Decodes the characters of a JSON string one at a time, escapes included
+#[derive(Default)]
+struct StringDecoder {
+    escape: Escape,
+    high_surrogate: Option<u32>,
+}
+
+#[derive(Default)]
+enum Escape {
+    #[default]
+    None,
+    Backslash,
+    Unicode { value: u32, digits: u8 },
+}
+
+impl StringDecoder {
+    fn feed(&mut self, c: char) -> Decoded {
+        match self.escape {
+            Escape::None => match c {
+                '"' => Decoded::End,
+                '\\' => {
+                    self.escape = Escape::Backslash;
+                    Decoded::Nothing
+                }
+                c => Decoded::Char(c),
+            },
+            Escape::Backslash => {
+                self.escape = Escape::None;
+                match c {
+                    'n' => Decoded::Char('\n'),
+                    't' => Decoded::Char('\t'),
+                    'r' => Decoded::Char('\r'),
+                    'b' => Decoded::Char('\u{8}'),
+                    'f' => Decoded::Char('\u{c}'),
+                    'u' => {
+                        self.escape = Escape::Unicode { value: 0, digits: 0 };
+                        Decoded::Nothing
+                    }
+                    // `\"`, `\\`, `\/` and anything unexpected stand for themselves
+                    c => Decoded::Char(c),
+                }
+            }
+            Escape::Unicode { value, digits } => {
+                let Some(digit) = c.to_digit(16) else {
+                    // Malformed escape, keep the character rather than lose text
+                    self.escape = Escape::None;
+                    return Decoded::Char(c);
+                };
+                let value = value * 16 + digit;
+                if digits < 3 {
+                    self.escape = Escape::Unicode { value, digits: digits + 1 };
+                    return Decoded::Nothing;
+                }
+
+                self.escape = Escape::None;
+                match (self.high_surrogate.take(), value) {
+                    (None, 0xD800..=0xDBFF) => {
+                        self.high_surrogate = Some(value);
+                        Decoded::Nothing
+                    }
+                    (Some(high), 0xDC00..=0xDFFF) => {
+                        let combined = 0x10000 + ((high - 0xD800) << 10) + (value - 0xDC00);
+                        Decoded::Char(char::from_u32(combined).unwrap_or('\u{FFFD}'))
+                    }
+                    (_, value) => Decoded::Char(char::from_u32(value).unwrap_or('\u{FFFD}')),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feed the chunks and return the text decoded per field path, in the order fields finished
+    fn collect(mut stream: JsonFieldStream, chunks: &[&str]) -> Vec<(String, String)> {
+        let mut fields: Vec<(String, String, bool)> = Vec::new();
+        for chunk in chunks {
+            for delta in stream.push(chunk) {
+                let path = delta.path.join(".");
+                match fields.iter_mut().find(|(p, _, done)| *p == path && !done) {
+                    Some((_, text, done)) => {
+                        text.push_str(&delta.text);
+                        *done = delta.done;
+                    }
+                    None => fields.push((path, delta.text, delta.done)),
+                }
+            }
+        }
+        assert!(fields.iter().all(|(_, _, done)| *done), "unfinished fields: {:?}", fields);
+        fields.into_iter().map(|(path, text, _)| (path, text)).collect()
+    }
+
+    /// Feed the text one character at a time
+    fn char_chunks(text: &str) -> Vec<String> {
+        text.chars().map(String::from).collect()
+    }
+
+    fn dialogue(chunks: &[&str]) -> String {
+        let fields = collect(JsonFieldStream::intent_dialogue(), chunks);
+        assert_eq!(fields.len(), 1, "{:?}", fields);
+        fields[0].1.clone()
+    }
+
+    #[test]
+    fn streams_text_before_the_string_closes() {
+        let mut stream = JsonFieldStream::intent_dialogue();
+        assert!(stream.push(r#"{"npc": "bob", "thought": "hmm", "#).is_empty());
+
+        let delta = stream.push(r#""dialogue": "Hel"#);
+        assert_eq!(delta, [FieldDelta { path: vec!["dialogue".to_string()], text: "Hel".to_string(), done: false }]);
+
+        let delta = stream.push(r#"lo"}"#);
+        assert_eq!(delta, [FieldDelta { path: vec!["dialogue".to_string()], text: "lo".to_string(), done: true }]);
+        assert!(stream.is_finished());
+    }
+
+    #[test]
+    fn skips_preamble_and_stops_after_the_object() {
+        let text = "Here you go:\n```json\n{\"dialogue\": \"Hi\"}\n```\n{\"dialogue\": \"again\"}";
+        assert_eq!(dialogue(&[text]), "Hi");
+    }
+
+    #[test]
+    fn ignores_fields_off_the_pattern() {
+        let text = r#"{"npc": "bob", "dialogue_hint": "no", "nested": {"dialogue": "no"}, "dialogue": "yes"}"#;
+        assert_eq!(dialogue(&[text]), "yes");
+    }
+
+    #[test]
+    fn null_dialogue_yields_nothing() {
+        let mut stream = JsonFieldStream::intent_dialogue();
+        assert!(stream.push(r#"{"dialogue": null, "action": "shrugs"}"#).is_empty());
+        assert!(stream.is_finished());
+    }
+
+    #[test]
+    fn decodes_escapes() {
+        let text = r#"{"dialogue": "Say \"hi\"\\\/\n\tok"}"#;
+        assert_eq!(dialogue(&[text]), "Say \"hi\"\\/\n\tok");
+    }
+
+    #[test]
+    fn decodes_unicode_escapes_split_across_chunks() {
+        let text = r#"{"dialogue": "caf\u00e9 na\u00EFve"}"#;
+        assert_eq!(dialogue(&[text]), "café naïve");
+        for split in 1..text.len() {
+            assert_eq!(dialogue(&[&text[..split], &text[split..]]), "café naïve", "split at {}", split);
+        }
+    }
+
+    #[test]
+    fn decodes_surrogate_pairs_split_across_chunks() {
+        let text = r#"{"dialogue": "ok \ud83d\ude00!"}"#;
+        assert_eq!(dialogue(&[text]), "ok 😀!");
+
+        // Between the two halves, inside either half, and one character at a time
+        assert_eq!(dialogue(&[r#"{"dialogue": "ok \ud83d"#, r#"\ude00!"}"#]), "ok 😀!");
+        assert_eq!(dialogue(&[r#"{"dialogue": "ok \ud8"#, r#"3d\ude"#, r#"00!"}"#]), "ok 😀!");
+        let chunks = char_chunks(text);
+        let chunks: Vec<&str> = chunks.iter().map(String::as_str).collect();
+        assert_eq!(dialogue(&chunks), "ok 😀!");
+    }
+
+    #[test]
+    fn replaces_lone_surrogates() {
+        assert_eq!(dialogue(&[r#"{"dialogue": "a\udc00b"}"#]), "a\u{FFFD}b");
+        assert_eq!(dialogue(&[r#"{"dialogue": "a\ud83dA"}"#]), "aA");
+    }
+
+    #[test]
+    fn keeps_multibyte_text_split_across_chunks() {
+        let text = r#"{"dialogue": "Grüß dich, 世界"}"#;
+        let chunks = char_chunks(text);
+        let chunks: Vec<&str> = chunks.iter().map(String::as_str).collect();
+        assert_eq!(dialogue(&chunks), "Grüß dich, 世界");
+    }
+
+    #[test]
+    fn matches_wildcards_and_array_indices() {
+        let text = r#"{
+            "reality": "They talk.",
+            "contracts": [
+                {"id": "c1", "transcript_entry": {"details": {"alice": {"action": "waves", "dialogue": "Hi"}}}},
+                {"id": "c2", "transcript_entry": null},
+                {"id": "c3", "transcript_entry": {"details": {
+                    "bob": {"action": "nods", "dialogue": "Yes"},
+                    "carol": {"dialogue": null, "tags": [], "mood": 3}
+                }}}
+            ],
+            "next_prompts": {"alice": "not dialogue"}
+        }"#;
+        let fields = collect(JsonFieldStream::gm_dialogue(), &[text]);
+        assert_eq!(
+            fields,
+            [
+                ("contracts.0.transcript_entry.details.alice.dialogue".to_string(), "Hi".to_string()),
+                ("contracts.2.transcript_entry.details.bob.dialogue".to_string(), "Yes".to_string()),
+            ]
+        );
+
+        let chunks = char_chunks(text);
+        let chunks: Vec<&str> = chunks.iter().map(String::as_str).collect();
+        assert_eq!(collect(JsonFieldStream::gm_dialogue(), &chunks), fields);
+    }
+
+    #[test]
+    fn transcript_dialogue_reads_each_participant() {
+        let text = r#"{"reality": "...", "details": {"alice": {"dialogue": "One"}, "bob": {"dialogue": "Two"}}}"#;
+        let fields = collect(JsonFieldStream::transcript_dialogue(), &[text]);
+        assert_eq!(
+            fields,
+            [
+                ("details.alice.dialogue".to_string(), "One".to_string()),
+                ("details.bob.dialogue".to_string(), "Two".to_string()),
+            ]
+        );
+    }
+}
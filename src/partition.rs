@@ -0,0 +1,278 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::types::{ContractUpdate, GameState, GmResponse, Intent, StateChange};
+use crate::validation::Diagnostic;
+use crate::world::World;
+
+/// A group of NPCs whose intents can affect each other, resolved by its own GM call
+///
+/// NPCs end up in the same partition when they share a location, share a contract, or one
+/// of their intents mentions the other or the other's location by name.
+#[derive(Debug, Clone, Default)]
+pub struct Partition {
+    /// Everyone the GM is shown: those acting and everyone around them
+    pub npcs: BTreeSet<String>,
+    /// Where those NPCs are, plus any location an intent mentions
+    pub locations: BTreeSet<String>,
+    /// The intents to resolve, in NPC name order
+    pub intents: Vec<Intent>,
+}
+
+/// Split intents into partitions, ordered by their first acting NPC
+///
+/// NPCs in partitions without any intent are left out, so idle corners of the map cost
+/// nothing to resolve.
+///
+/// ```
+/// use social_npc::{partition::partition_intents, GameState, Intent, Npc, WorldClock};
+/// use std::collections::HashMap;
+///
+/// let mut state = GameState { npcs: HashMap::new(), contracts: HashMap::new(), turn: 0, clock: WorldClock::default() };
+/// for (name, location) in [("alice", "tavern"), ("bob", "tavern"), ("carol", "forest")] {
+///     state.npcs.insert(name.to_string(), Npc::new(name, location, "idling"));
+/// }
+/// let intent = |npc: &str, action: &str| Intent {
+///     npc: npc.to_string(),
+///     thought: String::new(),
+///     action: action.to_string(),
+///     dialogue: None,
+/// };
+///
+/// let partitions = partition_intents(&[intent("alice", "orders an ale"), intent("carol", "gathers herbs")], &state, None);
+/// assert_eq!(partitions.len(), 2);
+/// assert!(partitions[0].npcs.contains("bob"));
+///
+/// // Heading for the tavern brings Carol into the tavern's partition
+/// let partitions = partition_intents(&[intent("alice", "orders an ale"), intent("carol", "walks to the tavern")], &state, None);
+/// assert_eq!(partitions.len(), 1);
+/// ```
+pub fn partition_intents(intents: &[Intent], game_state: &GameState, world: Option<&World>) -> Vec<Partition> {
+    let mut npc_names: Vec<&String> = game_state.npcs.keys().collect();
+    npc_names.sort();
+    let mut location_names: BTreeSet<&String> = game_state.npcs.values().map(|npc| &npc.location).collect();
+    if let Some(world) = world {
+        location_names.extend(world.locations.keys());
+    }
+
+    // NPCs come first, then locations, each one a set in the disjoint-set forest
+    let npc_index: HashMap<&str, usize> = npc_names.iter().enumerate().map(|(i, name)| (name.as_str(), i)).collect();
+    let location_index: HashMap<&str, usize> = location_names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (name.as_str(), npc_names.len() + i))
+        .collect();
+    let mut sets = DisjointSets::new(npc_names.len() + location_names.len());
+
+    // Keyed by the map key, which the rest of the engine uses, even if an NPC's name field differs
+    for (name, npc) in &game_state.npcs {
+        sets.union(npc_index[name.as_str()], location_index[npc.location.as_str()]);
+    }
+    for contract in game_state.contracts.values() {
+        let mut members = contract.participants.iter().filter_map(|name| npc_index.get(name.as_str()));
+        if let Some(&first) = members.next() {
+            for &other in members {
+                sets.union(first, other);
+            }
+        }
+    }
+    for intent in intents {
+        let Some(&actor) = npc_index.get(intent.npc.as_str()) else {
+            continue;
+        };
+        let text = format!("{} {}", intent.action, intent.dialogue.as_deref().unwrap_or_default()).to_lowercase();
+        for name in &npc_names {
+            if mentions(&text, name) {
+                sets.union(actor, npc_index[name.as_str()]);
+            }
+        }
+        for name in &location_names {
+            if mentions(&text, name) {
+                sets.union(actor, location_index[name.as_str()]);
+            }
+        }
+    }
+
+    let mut partitions: BTreeMap<usize, Partition> = BTreeMap::new();
+    let mut sorted_intents: Vec<&Intent> = intents.iter().collect();
+    sorted_intents.sort_by(|a, b| a.npc.cmp(&b.npc));
+    for intent in sorted_intents {
+        let Some(&actor) = npc_index.get(intent.npc.as_str()) else {
+            log::warn!("Intent from unknown NPC '{}' left out of GM partitions", intent.npc);
+            continue;
+        };
+        partitions.entry(sets.find(actor)).or_default().intents.push(intent.clone());
+    }
+    for name in &npc_names {
+        if let Some(partition) = partitions.get_mut(&sets.find(npc_index[name.as_str()])) {
+            partition.npcs.insert(name.to_string());
+        }
+    }
+    for name in &location_names {
+        if let Some(partition) = partitions.get_mut(&sets.find(location_index[name.as_str()])) {
+            partition.locations.insert(name.to_string());
+        }
+    }
+
+    let mut partitions: Vec<Partition> = partitions.into_values().collect();
+    partitions.sort_by(|a, b| a.intents[0].npc.cmp(&b.intents[0].npc));
+    partitions
+}
+
+/// Combine the GM responses for each partition into one, in partition order
+///
+/// `reality` joins every partition's account, while `local_realities` keeps each NPC to the
+/// account of their own partition.
+///
+/// Where partitions disagree about the same NPC or contract, the partition the NPC (or the
+/// contract's first participant) belongs to wins, otherwise the first one; each such clash
+/// is returned as a diagnostic.
+pub fn merge_responses(partitions: &[Partition], responses: Vec<GmResponse>) -> (GmResponse, Vec<Diagnostic>) {
+    let owner = |npc: &str| partitions.iter().position(|partition| partition.npcs.contains(npc));
+    let mut diagnostics = Vec::new();
+
+    let mut realities = Vec::new();
+    let mut local_realities = HashMap::new();
+    let mut state_changes: BTreeMap<String, (usize, StateChange)> = BTreeMap::new();
+    let mut contracts: Vec<(usize, ContractUpdate)> = Vec::new();
+    let mut next_prompts: BTreeMap<String, (usize, String)> = BTreeMap::new();
+
+    for (index, response) in responses.into_iter().enumerate() {
+        for npc in &partitions[index].npcs {
+            local_realities.entry(npc.clone()).or_insert_with(|| response.reality.clone());
+        }
+        realities.push(response.reality);
+
+        for change in response.state_changes {
+            if let Some(&(kept, _)) = state_changes.get(&change.npc) {
+                if kept != index {
+                    diagnostics.push(Diagnostic::ConflictingStateChanges { npc: change.npc.clone() });
+                    if owner(&change.npc) != Some(index) {
+                        continue;
+                    }
+                }
+            }
+            state_changes.insert(change.npc.clone(), (index, change));
+        }
+
+        for update in response.contracts {
+            let clash = contracts.iter().position(|(kept, other)| *kept != index && other.id == update.id);
+            let Some(position) = clash else {
+                contracts.push((index, update));
+                continue;
+            };
+            diagnostics.push(Diagnostic::ConflictingContracts { contract: update.id.clone() });
+            let first_participant = update.participants.first().map(String::as_str).unwrap_or_default();
+            if owner(first_participant) == Some(index) {
+                contracts[position] = (index, update);
+            }
+        }
+
+        for (npc, prompt) in response.next_prompts {
+            if let Some(&(kept, _)) = next_prompts.get(&npc) {
+                if kept != index {
+                    diagnostics.push(Diagnostic::ConflictingNextPrompts { npc: npc.clone() });
+                    if owner(&npc) != Some(index) {
+                        continue;
+                    }
+                }
+            }
+            next_prompts.insert(npc, (index, prompt));
+        }
+    }
+
+    diagnostics.dedup();
+    let merged = GmResponse {
+        reality: realities.join("\n\n"),
+        state_changes: state_changes.into_values().map(|(_, change)| change).collect(),
+        contracts: contracts.into_iter().map(|(_, update)| update).collect(),
+        next_prompts: next_prompts.into_iter().map(|(npc, (_, prompt))| (npc, prompt)).collect(),
+        local_realities,
+    };
+    (merged, diagnostics)
+}
+
+/// Whether `text` (lowercased) names `name` as a whole word, with underscores read as spaces
+pub(crate) fn mentions(text: &str, name: &str) -> bool {
+    mention_position(text, name).is_some()
+}
+
+/// Where `text` (lowercased) first names `name` as a whole word, with underscores read as spaces
+pub(crate) fn mention_position(text: &str, name: &str) -> Option<usize> {
+    let name = name.to_lowercase();
+    let spaced = name.replace('_', " ");
+    [name.as_str(), spaced.as_str()]
+        .iter()
+        .filter_map(|needle| {
+            text.match_indices(needle).map(|(start, _)| start).find(|&start| {
+                let end = start + needle.len();
+                let before = text[..start].chars().next_back();
+                let after = text[end..].chars().next();
+                !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+            })
+        })
+        .min()
+}
+
+/// Union-find over indices, for grouping NPCs and locations
+struct DisjointSets {
+    parents: Vec<usize>,
+}
+
+impl DisjointSets {
+    fn new(size: usize) -> Self {
+        Self { parents: (0..size).collect() }
+    }
+
+    fn find(&mut self, mut index: usize) -> usize {
+        while self.parents[index] != index {
+            self.parents[index] = self.parents[self.parents[index]];
+            index = self.parents[index];
+        }
+        index
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        // The smaller root wins so sets are keyed the same way every run
+        if a < b {
+            self.parents[b] = a;
+        } else {
+            self.parents[a] = b;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::WorldClock;
+    use crate::types::Npc;
+
+    fn intent(npc: &str, action: &str) -> Intent {
+        Intent {
+            npc: npc.to_string(),
+            thought: String::new(),
+            action: action.to_string(),
+            dialogue: None,
+        }
+    }
+
+    #[test]
+    fn npcs_are_grouped_by_their_key_not_their_name_field() {
+        let mut state = GameState {
+            npcs: HashMap::new(),
+            contracts: HashMap::new(),
+            turn: 0,
+            clock: WorldClock::default(),
+        };
+        state.npcs.insert("alice".to_string(), Npc::new("Alice Smith", "tavern", "idling"));
+        state.npcs.insert("bob".to_string(), Npc::new("bob", "tavern", "idling"));
+        state.npcs.insert("carol".to_string(), Npc::new("carol", "forest", "idling"));
+
+        let partitions = partition_intents(&[intent("alice", "waves"), intent("carol", "gathers herbs")], &state, None);
+
+        assert_eq!(partitions.len(), 2);
+        assert_eq!(partitions[0].npcs, ["alice", "bob"].map(String::from).into());
+        assert_eq!(partitions[1].npcs, ["carol"].map(String::from).into());
+    }
+}
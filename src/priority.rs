@@ -0,0 +1,54 @@
+use crate::types::{GameState, Npc};
+
+/// Decides which NPCs are asked for their intent first when concurrency is limited
+///
+/// Higher values go first; NPCs with equal priority go in name order. Any
+/// `Fn(&Npc, &GameState) -> i32` closure can be used as a priority.
+///
+/// ```rust,no_run
+/// use social_npc::{NpcEngine, llm::OllamaClient};
+///
+/// # fn example() -> social_npc::Result<()> {
+/// // The mayor always goes first
+/// let engine = NpcEngine::new("./data", OllamaClient::new("llama3.2:latest"))?
+///     .with_max_concurrent_intents(4)
+///     .with_intent_priority(|npc: &social_npc::Npc, _: &social_npc::GameState| {
+///         if npc.name == "mayor" { 1 } else { 0 }
+///     });
+/// # Ok(())
+/// # }
+/// ```
+pub trait IntentPriority: Send + Sync {
+    fn priority(&self, npc: &Npc, game_state: &GameState) -> i32;
+}
+
+impl<F> IntentPriority for F
+where
+    F: Fn(&Npc, &GameState) -> i32 + Send + Sync,
+{
+    fn priority(&self, npc: &Npc, game_state: &GameState) -> i32 {
+        self(npc, game_state)
+    }
+}
+
+/// Puts NPCs sharing a location with a player first, then NPCs in a contract with a player
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NearPlayers;
+
+impl IntentPriority for NearPlayers {
+    fn priority(&self, npc: &Npc, game_state: &GameState) -> i32 {
+        let mut players = game_state.npcs.values().filter(|other| other.player_controlled);
+
+        if players.clone().any(|player| player.location == npc.location) {
+            return 2;
+        }
+
+        let with_player = npc
+            .active_contract
+            .as_ref()
+            .and_then(|id| game_state.contracts.get(id))
+            .is_some_and(|contract| players.any(|player| contract.participants.contains(&player.name)));
+
+        i32::from(with_player)
+    }
+}
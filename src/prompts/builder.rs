@@ -1,23 +1,35 @@
-use anyhow::Result;
 use std::path::Path;
 use std::fs;
+use std::sync::Arc;
 use serde_json;
 
+use crate::error::{Error, IoResultExt, Result};
 use crate::types::{GameState, Npc};
+use crate::world::World;
 use crate::memory::MemorySystem;
+use crate::transcript::TranscriptStore;
 use super::loader::PromptLoader;
 
 /// Builds prompts for NPCs and the GM
 pub struct PromptBuilder {
     loader: PromptLoader,
+    transcripts: TranscriptStore,
     data_path: std::path::PathBuf,
+    world: Option<Arc<World>>,
 }
 
 impl PromptBuilder {
     pub fn new(data_path: impl AsRef<Path>) -> Self {
         let data_path = data_path.as_ref().to_path_buf();
         let loader = PromptLoader::new(&data_path);
-        Self { loader, data_path }
+        let transcripts = TranscriptStore::new(&data_path);
+        Self { loader, transcripts, data_path, world: None }
+    }
+
+    /// Describe NPCs' surroundings and where they can go from the world map
+    pub fn with_world(mut self, world: Arc<World>) -> Self {
+        self.world = Some(world);
+        self
     }
 
     /// Build a prompt for an NPC to decide their next action
@@ -39,22 +51,26 @@ impl PromptBuilder {
         // 3. Current memories
         if let Ok(memories) = self.load_memories(&npc.name) {
             sections.push(format!("## Your Current Memories\n\n```json\n{}\n```", 
-                serde_json::to_string_pretty(&memories)?));
+                to_stable_json(&memories)?));
         }
         
         // 4. Current state
         sections.push(self.format_current_state(npc, game_state));
         
-        // 5. Contract context if in one
+        // 5. Daily routine, nudging them towards what they'd usually be doing now
+        if !npc.schedule.is_empty() {
+            sections.push(self.format_routine(npc, game_state));
+        }
+        
+        // 6. Contract context if in one
         if let Some(contract_id) = &npc.active_contract {
             if let Ok(transcript) = self.read_contract_transcript(contract_id) {
                 sections.push(format!("## Current Interaction\n\n{}", transcript));
             }
         }
         
-        // 6. GM's specific prompt or generic "What do you do next?"
-        let prompt = npc.next_prompt.as_ref()
-            .map(|p| p.clone())
+        // 7. GM's specific prompt or generic "What do you do next?"
+        let prompt = npc.next_prompt.clone()
             .unwrap_or_else(|| "What do you do next?".to_string());
         sections.push(prompt);
 
@@ -92,7 +108,7 @@ impl PromptBuilder {
         // Load current memories
         if let Ok(memories) = self.load_memories(npc_name) {
             sections.push(format!("## Current Memories\n\n```json\n{}\n```", 
-                serde_json::to_string_pretty(&memories)?));
+                to_stable_json(&memories)?));
         }
         
         // Add context
@@ -106,20 +122,107 @@ impl PromptBuilder {
         Ok(sections.join("\n\n---\n\n"))
     }
 
+    /// Build a prompt for an NPC to answer something said to them directly
+    pub fn build_dialogue_prompt(
+        &self,
+        npc: &Npc,
+        game_state: &GameState,
+        speaker: &str,
+        utterance: &str,
+    ) -> Result<String> {
+        let mut sections = vec![];
+        
+        sections.push(self.loader.load_custom("dialogue")
+            .unwrap_or_else(|_| DIALOGUE_DEFAULT.to_string()));
+        
+        if let Ok(personality) = self.load_personality(&npc.name) {
+            sections.push(personality);
+        }
+        
+        // Only the memories that matter for this conversation
+        let memories = self.load_memories(&npc.name).unwrap_or_default();
+        sections.push(format!("## What You Remember\n\n```json\n{}\n```",
+            to_stable_json(&memories.self_memories)?));
+        
+        match memories.relationships.get(speaker) {
+            Some(relationship) => sections.push(format!("## You And {}\n\n```json\n{}\n```",
+                speaker, to_stable_json(relationship)?)),
+            None => sections.push(format!("## You And {}\n\nYou don't know {} yet.", speaker, speaker)),
+        }
+        
+        sections.push(self.format_current_state(npc, game_state));
+        
+        if let Some(contract_id) = &npc.active_contract {
+            if let Ok(transcript) = self.read_contract_transcript(contract_id) {
+                sections.push(format!("## Conversation So Far\n\n{}", transcript));
+            }
+        }
+        
+        sections.push(format!("## {} Says\n\n\"{}\"", speaker, utterance));
+        
+        Ok(sections.join("\n\n---\n\n"))
+    }
+
+    /// Build a prompt asking the LLM to correct a response that could not be parsed
+    pub fn build_repair_prompt(
+        &self,
+        original_prompt: &str,
+        failed_response: &str,
+        error: &str,
+    ) -> Result<String> {
+        let instructions = self.loader.load_custom("repair")
+            .unwrap_or_else(|_| REPAIR_DEFAULT.to_string());
+        
+        // Long garbage responses would crowd out the original instructions
+        let mut response: String = failed_response.chars().take(MAX_REPAIR_RESPONSE_CHARS).collect();
+        if response.len() < failed_response.len() {
+            response.push_str("\n[...truncated]");
+        }
+        
+        let sections = [
+            original_prompt.to_string(),
+            instructions,
+            format!("## Your Previous Response\n\n```\n{}\n```", response),
+            format!("## Problem\n\n{}", error),
+        ];
+        
+        Ok(sections.join("\n\n---\n\n"))
+    }
+
     fn format_current_state(&self, npc: &Npc, game_state: &GameState) -> String {
         let mut state = String::from("## Current Situation\n\n");
         
+        state.push_str(&format!("- It is: {}\n", game_state.clock.describe()));
+        
         // NPC's own state
         state.push_str(&format!("- You are at: {}\n", npc.location));
         state.push_str(&format!("- You are: {}\n", npc.activity));
+        if let Some(travel) = &npc.travel {
+            state.push_str(&format!("- You are travelling to {} ({} turn(s) to go)\n",
+                travel.destination, travel.turns_left()));
+        }
+        
+        // What the place looks like and where it leads
+        if let Some(location) = self.world.as_ref().and_then(|w| w.location(&npc.location)) {
+            if !location.description.is_empty() {
+                state.push_str(&format!("\nAround you: {}\n", location.description));
+            }
+            if !location.exits.is_empty() {
+                state.push_str("\nFrom here you can go to:\n");
+                for (to, turns) in &location.exits {
+                    state.push_str(&format!("- {} ({} turn(s) away)\n", to, turns));
+                }
+            }
+        }
         
         // Others at same location
-        let others_here: Vec<_> = game_state.npcs
+        let mut others_here: Vec<_> = game_state.npcs
             .iter()
             .filter(|(name, other_npc)| {
                 name.as_str() != npc.name.as_str() && other_npc.location == npc.location
             })
             .collect();
+        others_here.sort_by(|a, b| a.0.cmp(b.0));
             
         if !others_here.is_empty() {
             state.push_str("\nAlso here:\n");
@@ -141,13 +244,37 @@ impl PromptBuilder {
         state
     }
 
+    fn format_routine(&self, npc: &Npc, game_state: &GameState) -> String {
+        let mut routine = String::from("## Your Daily Routine\n\n");
+        
+        let current = npc.scheduled(&game_state.clock);
+        let mut entries: Vec<_> = npc.schedule.iter().collect();
+        entries.sort_by_key(|entry| entry.start_hour);
+        for entry in entries {
+            routine.push_str(&format!("- {:02}:00-{:02}:00: {} at {}", 
+                entry.start_hour, entry.end_hour, entry.activity, entry.location));
+            if current.is_some_and(|c| std::ptr::eq(c, entry)) {
+                routine.push_str(" (now)");
+            }
+            routine.push('\n');
+        }
+        
+        match current {
+            Some(entry) => routine.push_str(&format!(
+                "\nRight now you would usually be {} at {}. Stick to your routine unless something more pressing comes up.",
+                entry.activity, entry.location)),
+            None => routine.push_str("\nNothing is planned for this time of day."),
+        }
+        
+        routine
+    }
+
     fn load_personality(&self, npc_name: &str) -> Result<String> {
         let path = self.data_path
             .join("npcs")
             .join(npc_name)
             .join("personality.md");
-        fs::read_to_string(path)
-            .map_err(|e| anyhow::anyhow!("Failed to load personality: {}", e))
+        fs::read_to_string(&path).at(&path)
     }
 
     fn load_memories(&self, npc_name: &str) -> Result<MemorySystem> {
@@ -164,29 +291,46 @@ impl PromptBuilder {
                 .join("initial_memories.json");
             
             if initial_path.exists() {
-                let content = fs::read_to_string(initial_path)?;
+                let content = fs::read_to_string(&initial_path).at(&initial_path)?;
                 return serde_json::from_str(&content)
-                    .map_err(|e| anyhow::anyhow!("Failed to parse memories: {}", e));
+                    .map_err(|e| Error::invalid(format!("Failed to parse memories {:?}: {}", initial_path, e)));
             }
             
             // Return empty memory system if no files exist
             return Ok(MemorySystem::new());
         }
         
-        let content = fs::read_to_string(path)?;
+        let content = fs::read_to_string(&path).at(&path)?;
         serde_json::from_str(&content)
-            .map_err(|e| anyhow::anyhow!("Failed to parse memories: {}", e))
+            .map_err(|e| Error::invalid(format!("Failed to parse memories {:?}: {}", path, e)))
     }
 
     fn read_contract_transcript(&self, contract_id: &str) -> Result<String> {
-        let path = self.data_path
-            .join("contracts")
-            .join(format!("{}.json", contract_id));
-        fs::read_to_string(path)
-            .map_err(|e| anyhow::anyhow!("Failed to read contract: {}", e))
+        let transcript = self.transcripts.load(contract_id)?;
+        Ok(transcript.to_dialogue())
     }
 }
 
+/// Pretty-print JSON with map keys sorted, so identical data always produces an identical prompt
+fn to_stable_json(value: &impl serde::Serialize) -> Result<String> {
+    Ok(serde_json::to_string_pretty(&serde_json::to_value(value)?)?)
+}
+
+/// Longest failed response quoted back to the LLM in a repair prompt
+const MAX_REPAIR_RESPONSE_CHARS: usize = 4000;
+
+// Default repair prompt if not provided
+const REPAIR_DEFAULT: &str = r#"# Response Could Not Be Used
+
+Your previous response could not be parsed. It is shown below together with the problem.
+
+Respond again with ONLY valid JSON in exactly the format requested above:
+- No text before or after the JSON
+- Use double quotes for all keys and strings
+- Use null (not "null" or "None") for absent values
+- Include every required field
+"#;
+
 // Default memory update prompt if not provided
 const MEMORY_UPDATE_DEFAULT: &str = r#"# Memory Update
 
@@ -205,7 +349,6 @@ Respond with JSON in this format:
       "immediate_context": "Your current feeling about this NPC",
       "new_memory": {
         "event": "What happened with them",
-        "timestamp": "2024-01-01T00:00:00Z",
         "emotional_impact": "how it made you feel",
         "importance": 0.5
       },
@@ -221,4 +364,26 @@ Consider:
 - How did reality differ from your intent?
 - What did you learn about yourself or others?
 - How do you feel about what happened?
-"#;
\ No newline at end of file
+"#;
+
+// Default prompt for answering someone directly if not provided
+const DIALOGUE_DEFAULT: &str = r#"# Conversation
+
+IMPORTANT: You should ONLY return a JSON response. Do not create, write, or modify any files.
+
+Someone is talking to you directly. Answer in character: the way you would, given your personality, your memories and how you feel about the person speaking. Keep it natural and conversational, usually one to three sentences. You may refuse, deflect or stay silent if that is what you would do.
+
+## Response Format
+
+Respond with JSON in exactly this format:
+
+```json
+{
+  "thought": "What you privately think about what was said",
+  "action": "What you do while answering (e.g. 'leans on the counter')",
+  "dialogue": "What you say out loud (or null if you say nothing)"
+}
+```
+
+Use null (not "null" or "None") for absent values.
+"#;
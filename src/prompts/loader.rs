@@ -1,7 +1,8 @@
-use anyhow::Result;
 use std::path::{Path, PathBuf};
 use std::fs;
 
+use crate::error::{IoResultExt, Result};
+
 use super::templates::{NPC_BASE_DEFAULT, GM_BASE_DEFAULT};
 
 /// Loads prompt templates from the filesystem with fallback to defaults
@@ -27,8 +28,7 @@ impl PromptLoader {
         for path in possible_paths {
             if path.exists() {
                 log::debug!("Loading NPC base prompt from: {:?}", path);
-                return fs::read_to_string(path)
-                    .map_err(|e| anyhow::anyhow!("Failed to read NPC base prompt: {}", e));
+                return fs::read_to_string(&path).at(&path);
             }
         }
 
@@ -47,8 +47,7 @@ impl PromptLoader {
         for path in possible_paths {
             if path.exists() {
                 log::debug!("Loading GM base prompt from: {:?}", path);
-                return fs::read_to_string(path)
-                    .map_err(|e| anyhow::anyhow!("Failed to read GM base prompt: {}", e));
+                return fs::read_to_string(&path).at(&path);
             }
         }
 
@@ -59,7 +58,6 @@ impl PromptLoader {
     /// Load a custom prompt template
     pub fn load_custom(&self, name: &str) -> Result<String> {
         let path = self.prompts_dir.join(format!("{}.md", name));
-        fs::read_to_string(&path)
-            .map_err(|e| anyhow::anyhow!("Failed to read prompt '{}': {}", name, e))
+        fs::read_to_string(&path).at(&path)
     }
 }
\ No newline at end of file
@@ -0,0 +1,275 @@
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+use crate::types::{GameState, Npc};
+
+/// Decides whether an NPC is worth an LLM call this turn
+///
+/// NPCs the gate lets sleep carry on with their current activity: they get a cheap
+/// "continues ..." intent that isn't sent to the GM or used to update memories.
+///
+/// ```rust,no_run
+/// use social_npc::{DefaultRelevanceGate, NpcEngine, llm::OllamaClient};
+///
+/// # fn example() -> social_npc::Result<()> {
+/// let engine = NpcEngine::new("./data", OllamaClient::new("llama3.2:latest"))?
+///     .with_relevance_gate(DefaultRelevanceGate::new().with_wake_chance(0.05).with_max_idle_turns(12));
+/// # Ok(())
+/// # }
+/// ```
+pub trait RelevanceGate: Send + Sync {
+    /// Why the NPC should be asked this turn, or None to let them carry on
+    fn wake_reason(&self, context: &RelevanceContext) -> Option<WakeReason>;
+}
+
+/// What a relevance gate knows about an NPC when deciding
+pub struct RelevanceContext<'a> {
+    pub npc: &'a Npc,
+    pub game_state: &'a GameState,
+    /// What the NPC saw the last time they were asked, None if they never were
+    pub last_seen: Option<&'a Surroundings>,
+    /// Turns in a row the NPC has been left idle
+    pub idle_turns: u64,
+    /// Whether the GM wrote the NPC a next prompt while they were idle
+    pub prompt_pending: bool,
+}
+
+/// What an NPC can see of their situation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Surroundings {
+    pub location: String,
+    pub activity: String,
+    /// Everyone else at the same location
+    pub company: BTreeSet<String>,
+    pub contract: Option<String>,
+    /// Location and activity of the schedule block for the current hour
+    pub scheduled: Option<(String, String)>,
+}
+
+impl Surroundings {
+    pub fn observe(npc: &Npc, game_state: &GameState) -> Self {
+        Self {
+            location: npc.location.clone(),
+            activity: npc.activity.clone(),
+            company: game_state
+                .npcs
+                .values()
+                .filter(|other| other.name != npc.name && other.location == npc.location)
+                .map(|other| other.name.clone())
+                .collect(),
+            contract: npc.active_contract.clone(),
+            scheduled: npc
+                .scheduled(&game_state.clock)
+                .map(|entry| (entry.location.clone(), entry.activity.clone())),
+        }
+    }
+}
+
+/// Why a relevance gate woke an NPC
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum WakeReason {
+    /// The NPC hasn't been asked before
+    FirstTurn,
+    /// The GM addressed the NPC while they were idle
+    PendingPrompt,
+    /// The NPC is in an interaction
+    InContract,
+    /// The NPC is somewhere else than when they were last asked
+    Moved,
+    /// The NPC is doing something else than when they were last asked
+    ActivityChanged,
+    /// Someone arrived or left
+    CompanyChanged { arrived: Vec<String>, left: Vec<String> },
+    /// The NPC's routine calls for something else now
+    ScheduleChanged,
+    /// The NPC has been idle for the maximum number of turns
+    IdleTooLong,
+    /// Chance woke the NPC, so quiet corners of the world still move now and then
+    RandomWakeUp,
+}
+
+/// Wakes NPCs when their surroundings change, now and then at random, and after too long idle
+#[derive(Debug, Clone)]
+pub struct DefaultRelevanceGate {
+    wake_chance: f64,
+    max_idle_turns: Option<u64>,
+    seed: u64,
+}
+
+impl DefaultRelevanceGate {
+    /// A gate with a 10% chance of waking idle NPCs each turn and no idle limit
+    pub fn new() -> Self {
+        Self {
+            wake_chance: 0.1,
+            max_idle_turns: None,
+            seed: 0,
+        }
+    }
+
+    /// Chance (0 to 1) of waking an NPC whose surroundings didn't change
+    pub fn with_wake_chance(mut self, chance: f64) -> Self {
+        self.wake_chance = chance.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Always wake NPCs who have been idle this many turns in a row
+    pub fn with_max_idle_turns(mut self, turns: u64) -> Self {
+        self.max_idle_turns = Some(turns);
+        self
+    }
+
+    /// Seed for random wake-ups; the same seed wakes the same NPCs on the same turns
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// A number in [0, 1) fixed by the seed, NPC and turn
+    fn roll(&self, npc: &str, turn: u64) -> f64 {
+        let mut x = self.seed ^ turn.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        for byte in npc.bytes() {
+            x = (x ^ byte as u64).wrapping_mul(0x0100_0000_01B3);
+        }
+        // splitmix64 finalizer
+        x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        x ^= x >> 31;
+        (x >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+impl Default for DefaultRelevanceGate {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RelevanceGate for DefaultRelevanceGate {
+    fn wake_reason(&self, context: &RelevanceContext) -> Option<WakeReason> {
+        let Some(last) = context.last_seen else {
+            return Some(WakeReason::FirstTurn);
+        };
+        if context.prompt_pending {
+            return Some(WakeReason::PendingPrompt);
+        }
+
+        let now = Surroundings::observe(context.npc, context.game_state);
+        if now.contract.is_some() {
+            return Some(WakeReason::InContract);
+        }
+        if now.location != last.location {
+            return Some(WakeReason::Moved);
+        }
+        if now.activity != last.activity {
+            return Some(WakeReason::ActivityChanged);
+        }
+        if now.company != last.company {
+            return Some(WakeReason::CompanyChanged {
+                arrived: now.company.difference(&last.company).cloned().collect(),
+                left: last.company.difference(&now.company).cloned().collect(),
+            });
+        }
+        if now.scheduled != last.scheduled {
+            return Some(WakeReason::ScheduleChanged);
+        }
+        if self.max_idle_turns.is_some_and(|max| context.idle_turns >= max) {
+            return Some(WakeReason::IdleTooLong);
+        }
+        if self.roll(&context.npc.name, context.game_state.turn) < self.wake_chance {
+            return Some(WakeReason::RandomWakeUp);
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::WorldClock;
+    use std::collections::HashMap;
+
+    /// Alice and Bob in the tavern, Carol on the square
+    fn state() -> GameState {
+        let mut state = GameState {
+            npcs: HashMap::new(),
+            contracts: HashMap::new(),
+            turn: 3,
+            clock: WorldClock::default(),
+        };
+        for (name, location) in [("alice", "tavern"), ("bob", "tavern"), ("carol", "square")] {
+            state.npcs.insert(name.to_string(), Npc::new(name, location, "idling"));
+        }
+        state
+    }
+
+    fn wake(state: &GameState, last_seen: &Surroundings, prompt_pending: bool) -> Option<WakeReason> {
+        let context = RelevanceContext {
+            npc: &state.npcs["alice"],
+            game_state: state,
+            last_seen: Some(last_seen),
+            idle_turns: 5,
+            prompt_pending,
+        };
+        DefaultRelevanceGate::new().with_wake_chance(0.0).wake_reason(&context)
+    }
+
+    #[test]
+    fn unchanged_surroundings_stay_idle() {
+        let state = state();
+        let last_seen = Surroundings::observe(&state.npcs["alice"], &state);
+
+        assert_eq!(wake(&state, &last_seen, false), None);
+    }
+
+    #[test]
+    fn never_asked_wakes() {
+        let state = state();
+        let context = RelevanceContext {
+            npc: &state.npcs["alice"],
+            game_state: &state,
+            last_seen: None,
+            idle_turns: 0,
+            prompt_pending: false,
+        };
+
+        assert_eq!(DefaultRelevanceGate::new().wake_reason(&context), Some(WakeReason::FirstTurn));
+    }
+
+    #[test]
+    fn arrival_wakes() {
+        let mut state = state();
+        let last_seen = Surroundings::observe(&state.npcs["alice"], &state);
+        state.npcs.get_mut("carol").unwrap().location = "tavern".to_string();
+
+        assert_eq!(
+            wake(&state, &last_seen, false),
+            Some(WakeReason::CompanyChanged { arrived: vec!["carol".to_string()], left: Vec::new() })
+        );
+    }
+
+    #[test]
+    fn pending_prompt_wakes() {
+        let state = state();
+        let last_seen = Surroundings::observe(&state.npcs["alice"], &state);
+
+        assert_eq!(wake(&state, &last_seen, true), Some(WakeReason::PendingPrompt));
+    }
+
+    #[test]
+    fn idle_limit_wakes() {
+        let state = state();
+        let last_seen = Surroundings::observe(&state.npcs["alice"], &state);
+        let context = RelevanceContext {
+            npc: &state.npcs["alice"],
+            game_state: &state,
+            last_seen: Some(&last_seen),
+            idle_turns: 5,
+            prompt_pending: false,
+        };
+        let gate = DefaultRelevanceGate::new().with_wake_chance(0.0).with_max_idle_turns(5);
+
+        assert_eq!(gate.wake_reason(&context), Some(WakeReason::IdleTooLong));
+    }
+}
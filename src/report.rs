@@ -0,0 +1,176 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use crate::llm::LlmRole;
+use crate::retry::RepairRecord;
+use crate::types::{GameState, GmResponse, Intent};
+use crate::validation::Diagnostic;
+
+/// Everything that happened during one call to `NpcEngine::execute_turn`
+#[derive(Debug, Clone, Serialize)]
+pub struct TurnReport {
+    /// The turn number this report is for, counting from 1
+    pub turn: u64,
+    /// Intents collected from NPCs, in NPC name order
+    pub intents: Vec<Intent>,
+    /// The GM's resolution, as applied (after any validation fixes)
+    pub reality: GmResponse,
+    /// Problems found in the GM response
+    pub diagnostics: Vec<Diagnostic>,
+    /// How the world changed during resolution
+    pub changes: StateDiff,
+    /// NPCs that failed to produce an intent or update their memories
+    pub errors: Vec<NpcError>,
+    /// NPCs that missed the intent deadline and continued their current activity
+    pub fallbacks: Vec<String>,
+    /// NPCs that followed their schedule without LLM calls, see `NpcEngine::with_auto_routines`
+    pub routines: Vec<String>,
+    /// Intents for NPCs the relevance gate let carry on, not sent to the GM, see
+    /// `NpcEngine::with_relevance_gate`
+    pub idle: Vec<Intent>,
+    pub durations: PhaseDurations,
+    /// LLM usage during the turn, per call site
+    pub llm_calls: HashMap<LlmRole, LlmCallStats>,
+    /// LLM calls that needed retries or JSON repairs
+    pub repairs: Vec<RepairRecord>,
+}
+
+impl TurnReport {
+    /// Whether the turn went through without errors, fallbacks, validation problems or repairs
+    pub fn is_clean(&self) -> bool {
+        self.errors.is_empty()
+            && self.fallbacks.is_empty()
+            && self.diagnostics.is_empty()
+            && self.repairs.is_empty()
+    }
+
+    /// Total LLM queries sent during the turn, including retries
+    pub fn total_llm_attempts(&self) -> u32 {
+        self.llm_calls.values().map(|s| s.attempts).sum()
+    }
+}
+
+/// The stage of a turn an error happened in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TurnPhase {
+    Intents,
+    Memories,
+}
+
+/// An NPC that dropped out of part of a turn
+#[derive(Debug, Clone, Serialize)]
+pub struct NpcError {
+    pub npc: String,
+    pub phase: TurnPhase,
+    pub error: String,
+}
+
+/// Wall-clock time spent in each phase of a turn
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PhaseDurations {
+    pub intents: Duration,
+    pub resolution: Duration,
+    pub memories: Duration,
+    /// The whole turn, including snapshots
+    pub total: Duration,
+}
+
+/// LLM usage for one call site
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LlmCallStats {
+    /// Calls made by the engine
+    pub calls: u32,
+    /// Queries sent, including retries
+    pub attempts: u32,
+    /// Calls that never produced usable output
+    pub failures: u32,
+    /// Calls whose output needed retries or JSON repairs
+    pub repaired: u32,
+}
+
+impl LlmCallStats {
+    /// Tally the records of a set of calls per call site
+    pub fn from_records(records: &[RepairRecord]) -> HashMap<LlmRole, LlmCallStats> {
+        let mut stats: HashMap<LlmRole, LlmCallStats> = HashMap::new();
+        for record in records {
+            let entry = stats.entry(record.context.role).or_default();
+            entry.calls += 1;
+            entry.attempts += record.attempts;
+            if !record.succeeded {
+                entry.failures += 1;
+            } else if !record.is_clean() {
+                entry.repaired += 1;
+            }
+        }
+        stats
+    }
+}
+
+/// Differences in NPC state and contracts between two points in a game
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StateDiff {
+    /// NPCs whose location or activity changed, by name
+    pub npcs: Vec<NpcChange>,
+    pub contracts_created: Vec<String>,
+    pub contracts_ended: Vec<String>,
+}
+
+impl StateDiff {
+    /// Compare two game states, listing everything in name order
+    pub fn between(before: &GameState, after: &GameState) -> Self {
+        let mut npcs: Vec<NpcChange> = after
+            .npcs
+            .iter()
+            .filter_map(|(name, npc)| {
+                let old = before.npcs.get(name)?;
+                if old.location == npc.location && old.activity == npc.activity {
+                    return None;
+                }
+                Some(NpcChange {
+                    npc: name.clone(),
+                    from_location: old.location.clone(),
+                    to_location: npc.location.clone(),
+                    from_activity: old.activity.clone(),
+                    to_activity: npc.activity.clone(),
+                })
+            })
+            .collect();
+        npcs.sort_by(|a, b| a.npc.cmp(&b.npc));
+
+        let before_ids: HashSet<_> = before.contracts.keys().collect();
+        let after_ids: HashSet<_> = after.contracts.keys().collect();
+
+        let mut contracts_created: Vec<String> = after_ids.difference(&before_ids).map(|id| id.to_string()).collect();
+        contracts_created.sort();
+        let mut contracts_ended: Vec<String> = before_ids.difference(&after_ids).map(|id| id.to_string()).collect();
+        contracts_ended.sort();
+
+        Self {
+            npcs,
+            contracts_created,
+            contracts_ended,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.npcs.is_empty() && self.contracts_created.is_empty() && self.contracts_ended.is_empty()
+    }
+}
+
+/// How one NPC's location and activity changed
+#[derive(Debug, Clone, Serialize)]
+pub struct NpcChange {
+    pub npc: String,
+    pub from_location: String,
+    pub to_location: String,
+    pub from_activity: String,
+    pub to_activity: String,
+}
+
+impl NpcChange {
+    pub fn moved(&self) -> bool {
+        self.from_location != self.to_location
+    }
+}
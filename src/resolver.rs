@@ -0,0 +1,358 @@
+use async_trait::async_trait;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use crate::engine::NpcEngine;
+use crate::error::Result;
+use crate::partition::mention_position;
+use crate::types::{ContractUpdate, GmInput, GmResponse, Intent, NpcAction, StateChange, TranscriptEntry};
+
+/// Decides what actually happens when NPCs act, for `NpcEngine::resolve_intents`
+///
+/// The engine validates and applies whatever the resolver returns, exactly as it does
+/// for the LLM GM. With partitioned resolution the resolver is called once per partition.
+///
+/// ```rust,no_run
+/// use social_npc::{NpcEngine, RuleBasedResolver, llm::OllamaClient};
+///
+/// # fn example() -> social_npc::Result<()> {
+/// // Resolve turns with the LLM, but keep the game going if the GM call fails
+/// let engine = NpcEngine::new("./data", OllamaClient::new("llama3.2:latest"))?
+///     .with_gm_fallback(RuleBasedResolver);
+/// # Ok(())
+/// # }
+/// ```
+#[async_trait]
+pub trait GmResolver: Send + Sync {
+    async fn resolve(&self, input: &GmInput, context: &ResolveContext<'_>) -> Result<GmResponse>;
+}
+
+/// What the engine lends a resolver while it resolves a turn
+pub struct ResolveContext<'a> {
+    pub(crate) engine: &'a NpcEngine,
+}
+
+impl ResolveContext<'_> {
+    /// Ask the engine's LLM to resolve `input` with the GM prompt, using the engine's retry
+    /// policy, events and streaming
+    pub async fn query_llm(&self, input: &GmInput) -> Result<GmResponse> {
+        self.engine.query_gm_llm(input).await
+    }
+}
+
+/// The LLM Game Master, the engine's default resolver
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LlmResolver;
+
+#[async_trait]
+impl GmResolver for LlmResolver {
+    async fn resolve(&self, input: &GmInput, context: &ResolveContext<'_>) -> Result<GmResponse> {
+        context.query_llm(input).await
+    }
+}
+
+/// Resolves intents with fixed rules and no LLM calls, for offline play, CI and as a fallback
+///
+/// - NPCs whose action names a known location (on the world map, or where anyone is) go
+///   there; everyone else takes up their action as their activity
+/// - NPCs who name someone at their location start a conversation with them, unless either
+///   is already in one or on their way out
+/// - Whatever participants of a conversation do and say goes into its transcript, and the
+///   conversation ends when one of them leaves
+/// - Everyone who acted or was spoken to is told what happened around them
+///
+/// ```
+/// use social_npc::{CurrentState, GmInput, Intent, Npc, RuleBasedResolver};
+/// use std::collections::HashMap;
+///
+/// let npcs: HashMap<String, Npc> = [("alice", "tavern"), ("bob", "tavern"), ("carol", "square")]
+///     .into_iter()
+///     .map(|(name, location)| (name.to_string(), Npc::new(name, location, "idling")))
+///     .collect();
+/// let intent = |npc: &str, action: &str, dialogue: Option<&str>| Intent {
+///     npc: npc.to_string(),
+///     thought: String::new(),
+///     action: action.to_string(),
+///     dialogue: dialogue.map(str::to_string),
+/// };
+/// let input = GmInput {
+///     current_state: CurrentState {
+///         time: "day 1, 08:00 (morning)".to_string(),
+///         minutes_per_turn: 30,
+///         npcs,
+///         active_contracts: HashMap::new(),
+///     },
+///     intents: vec![
+///         intent("alice", "waves at Bob", Some("Morning, Bob!")),
+///         intent("carol", "heads to the tavern", None),
+///     ],
+///     locations: None,
+/// };
+///
+/// let response = RuleBasedResolver.resolve_input(&input);
+/// assert_eq!(response.contracts[0].id, "conv_alice_bob_day1_0800");
+/// assert_eq!(response.state_changes.iter().find(|c| c.npc == "carol").unwrap().location, "tavern");
+/// assert!(response.next_prompts.contains_key("bob"));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RuleBasedResolver;
+
+impl RuleBasedResolver {
+    /// Resolve a turn straight away; this is all `resolve` does
+    pub fn resolve_input(&self, input: &GmInput) -> GmResponse {
+        let state = &input.current_state;
+        let mut intents: Vec<&Intent> = input.intents.iter().filter(|intent| state.npcs.contains_key(&intent.npc)).collect();
+        intents.sort_by(|a, b| a.npc.cmp(&b.npc));
+
+        let mut known_locations: BTreeSet<&str> = state.npcs.values().map(|npc| npc.location.as_str()).collect();
+        if let Some(locations) = &input.locations {
+            known_locations.extend(locations.keys().map(String::as_str));
+        }
+
+        // Where everyone ends up and what they are doing
+        let mut state_changes = Vec::new();
+        let mut movers = HashSet::new();
+        let mut location_after: HashMap<&str, &str> = HashMap::new();
+        for intent in &intents {
+            let npc = &state.npcs[&intent.npc];
+            let action = intent.action.to_lowercase();
+            let destination = known_locations
+                .iter()
+                .filter(|location| **location != npc.location)
+                .filter_map(|location| Some((mention_position(&action, location)?, *location)))
+                .min_by_key(|(position, location)| (*position, std::cmp::Reverse(location.len())))
+                .map(|(_, location)| location);
+
+            let location = destination.unwrap_or(&npc.location);
+            // Intents from NPCs left to carry on shouldn't stack "continues" onto their activity
+            let activity = if intent.action == format!("continues {}", npc.activity) {
+                npc.activity.clone()
+            } else {
+                intent.action.trim_end_matches('.').to_string()
+            };
+            if destination.is_some() {
+                movers.insert(intent.npc.as_str());
+                location_after.insert(&intent.npc, location);
+            }
+            if location != npc.location || activity != npc.activity {
+                state_changes.push(StateChange {
+                    npc: intent.npc.clone(),
+                    location: location.to_string(),
+                    activity,
+                });
+            }
+        }
+
+        let by_npc: HashMap<&str, &Intent> = intents.iter().map(|intent| (intent.npc.as_str(), *intent)).collect();
+        let entry_for = |participants: &[String]| {
+            let acting: Vec<&Intent> = participants.iter().filter_map(|name| by_npc.get(name.as_str()).copied()).collect();
+            TranscriptEntry {
+                reality: acting.iter().map(|intent| describe(intent)).collect::<Vec<_>>().join(" "),
+                details: acting
+                    .iter()
+                    .map(|intent| {
+                        (intent.npc.clone(), NpcAction {
+                            action: intent.action.clone(),
+                            dialogue: intent.dialogue.clone(),
+                        })
+                    })
+                    .collect(),
+            }
+        };
+
+        // Conversations already going on carry on, or end when someone walks off
+        let mut contracts = Vec::new();
+        let mut in_contract: HashSet<&str> = HashSet::new();
+        let mut active: Vec<_> = state.active_contracts.values().collect();
+        active.sort_by(|a, b| a.id.cmp(&b.id));
+        for contract in active {
+            in_contract.extend(contract.participants.iter().map(String::as_str));
+            if !contract.participants.iter().any(|name| by_npc.contains_key(name.as_str())) {
+                continue;
+            }
+            let leaving = contract.participants.iter().any(|name| movers.contains(name.as_str()));
+            contracts.push(ContractUpdate {
+                id: contract.id.clone(),
+                participants: contract.participants.clone(),
+                action: if leaving { "end" } else { "update" }.to_string(),
+                transcript_entry: Some(entry_for(&contract.participants)),
+            });
+        }
+
+        // Naming someone nearby starts a conversation with them
+        let stamp = id_stamp(&state.time);
+        let mut addressed = BTreeSet::new();
+        for intent in &intents {
+            let speaker = &state.npcs[&intent.npc];
+            if movers.contains(speaker.name.as_str()) || in_contract.contains(speaker.name.as_str()) {
+                continue;
+            }
+            let text = format!("{} {}", intent.action, intent.dialogue.as_deref().unwrap_or_default()).to_lowercase();
+            let listener = state
+                .npcs
+                .values()
+                .filter(|other| other.name != speaker.name && other.location == speaker.location)
+                .filter_map(|other| Some((mention_position(&text, &other.name)?, other.name.as_str())))
+                .min()
+                .map(|(_, name)| name);
+            let Some(listener) = listener else {
+                continue;
+            };
+            addressed.insert(listener);
+            if movers.contains(listener) || in_contract.contains(listener) {
+                continue;
+            }
+
+            let mut participants = vec![speaker.name.clone(), listener.to_string()];
+            participants.sort();
+            in_contract.extend([speaker.name.as_str(), listener]);
+            contracts.push(ContractUpdate {
+                id: format!("conv_{}_{}", participants.join("_"), stamp),
+                transcript_entry: Some(entry_for(&participants)),
+                participants,
+                action: "create".to_string(),
+            });
+        }
+
+        // Everyone sees what happened where they were and where they end up
+        let seen_at: Vec<(&str, &str, &str, String)> = intents
+            .iter()
+            .map(|intent| {
+                let from = state.npcs[&intent.npc].location.as_str();
+                let to = location_after.get(intent.npc.as_str()).copied().unwrap_or(from);
+                (intent.npc.as_str(), from, to, describe(intent))
+            })
+            .collect();
+        let mut next_prompts = HashMap::new();
+        let audience: BTreeSet<&str> = intents.iter().map(|intent| intent.npc.as_str()).chain(addressed).collect();
+        for name in audience {
+            let npc = &state.npcs[name];
+            let location = location_after.get(name).copied().unwrap_or(&npc.location);
+            let around: Vec<&str> = seen_at
+                .iter()
+                .filter(|(actor, from, to, _)| *actor != name && (*from == location || *to == location))
+                .map(|(_, _, _, seen)| seen.as_str())
+                .collect();
+
+            let mut prompt = if movers.contains(name) {
+                format!("You arrive at {}.", location)
+            } else {
+                format!("You are at {}.", location)
+            };
+            if !around.is_empty() {
+                prompt.push(' ');
+                prompt.push_str(&around.join(" "));
+            }
+            prompt.push_str(" What do you do next?");
+            next_prompts.insert(name.to_string(), prompt);
+        }
+
+        let reality = if intents.is_empty() {
+            "Nothing happened.".to_string()
+        } else {
+            intents.iter().map(|intent| describe(intent)).collect::<Vec<_>>().join(" ")
+        };
+
+        GmResponse {
+            reality,
+            state_changes,
+            contracts,
+            next_prompts,
+            local_realities: HashMap::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl GmResolver for RuleBasedResolver {
+    async fn resolve(&self, input: &GmInput, _context: &ResolveContext<'_>) -> Result<GmResponse> {
+        Ok(self.resolve_input(input))
+    }
+}
+
+/// One sentence saying what an NPC did and said
+fn describe(intent: &Intent) -> String {
+    let mut sentence = format!("{} {}.", intent.npc, intent.action.trim_end_matches('.'));
+    if let Some(dialogue) = intent.dialogue.as_deref().filter(|dialogue| !dialogue.is_empty()) {
+        sentence.push_str(&format!(" {} says: \"{}\"", intent.npc, dialogue));
+    }
+    sentence
+}
+
+/// "day 3, 06:30 (dawn)" as "day3_0630", to keep contract ids unique over time
+fn id_stamp(time: &str) -> String {
+    let time = time.split(" (").next().unwrap_or(time);
+    time.split(',')
+        .map(|part| part.chars().filter(|c| c.is_alphanumeric()).collect::<String>())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Contract, CurrentState, Npc};
+
+    fn intent(npc: &str, action: &str) -> Intent {
+        Intent {
+            npc: npc.to_string(),
+            thought: String::new(),
+            action: action.to_string(),
+            dialogue: None,
+        }
+    }
+
+    /// Alice and Bob talking in the tavern, Carol on the square
+    fn input(intents: Vec<Intent>) -> GmInput {
+        let mut npcs: HashMap<String, Npc> = [("alice", "tavern"), ("bob", "tavern"), ("carol", "square")]
+            .into_iter()
+            .map(|(name, location)| (name.to_string(), Npc::new(name, location, "chatting")))
+            .collect();
+        let contract = Contract {
+            id: "talk".to_string(),
+            participants: vec!["alice".to_string(), "bob".to_string()],
+            transcript_file: "talk.json".to_string(),
+        };
+        for name in &contract.participants {
+            npcs.get_mut(name).unwrap().active_contract = Some(contract.id.clone());
+        }
+        GmInput {
+            current_state: CurrentState {
+                time: "day 2, 19:30 (evening)".to_string(),
+                minutes_per_turn: 30,
+                npcs,
+                active_contracts: HashMap::from([(contract.id.clone(), contract)]),
+            },
+            intents,
+            locations: None,
+        }
+    }
+
+    #[test]
+    fn conversations_go_on_until_someone_leaves() {
+        let intents = vec![intent("alice", "laughs"), intent("bob", "shrugs")];
+        let response = RuleBasedResolver.resolve_input(&input(intents));
+        assert_eq!(response.contracts.len(), 1);
+        assert_eq!(response.contracts[0].action, "update");
+        let details = &response.contracts[0].transcript_entry.as_ref().unwrap().details;
+        assert_eq!(details["bob"].action, "shrugs");
+
+        let response = RuleBasedResolver.resolve_input(&input(vec![intent("bob", "walks out to the square")]));
+        assert_eq!(response.contracts[0].action, "end");
+        assert_eq!(response.state_changes[0].location, "square");
+        assert!(response.next_prompts["bob"].starts_with("You arrive at square."));
+    }
+
+    #[test]
+    fn carrying_on_keeps_the_activity() {
+        let response = RuleBasedResolver.resolve_input(&input(vec![intent("carol", "continues chatting")]));
+        assert!(response.state_changes.is_empty());
+        assert_eq!(response.reality, "carol continues chatting.");
+    }
+
+    #[test]
+    fn unknown_npcs_are_ignored() {
+        let response = RuleBasedResolver.resolve_input(&input(vec![intent("mallory", "steals a purse")]));
+        assert!(response.state_changes.is_empty() && response.contracts.is_empty() && response.next_prompts.is_empty());
+        assert_eq!(response.reality, "Nothing happened.");
+    }
+}
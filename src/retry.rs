@@ -0,0 +1,236 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::error::{Error, Result};
+use crate::llm::{LlmClient, QueryContext};
+use crate::parser::{self, JsonRepair};
+use crate::prompts::PromptBuilder;
+
+/// How often and how patiently to re-query the LLM when its output can't be used
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total attempts per call, including the first
+    pub max_attempts: u32,
+    /// Delay before the first retry
+    pub initial_backoff: Duration,
+    /// Factor the delay grows by after each retry
+    pub backoff_multiplier: f32,
+    /// Upper bound on the delay between retries
+    pub max_backoff: Duration,
+    /// Also retry when the LLM call itself fails (timeouts, HTTP errors), not just when parsing fails
+    ///
+    /// Off by default: a call that timed out is likely to time out again, multiplying the wait.
+    pub retry_llm_errors: bool,
+}
+
+impl RetryPolicy {
+    /// Retry up to `max_attempts` total attempts with the default backoff
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            ..Self::default()
+        }
+    }
+
+    /// Never retry: a single attempt per call
+    pub fn none() -> Self {
+        Self::new(1)
+    }
+
+    pub fn with_backoff(mut self, initial: Duration, multiplier: f32) -> Self {
+        self.initial_backoff = initial;
+        self.backoff_multiplier = multiplier;
+        self
+    }
+
+    pub fn with_max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    pub fn with_retry_llm_errors(mut self, retry: bool) -> Self {
+        self.retry_llm_errors = retry;
+        self
+    }
+
+    /// Delay before the given retry (1 for the first retry)
+    pub fn backoff_for(&self, retry: u32) -> Duration {
+        // Grown in f64 and capped before it becomes a Duration, which can't hold huge values
+        let exponent = retry.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let factor = (self.backoff_multiplier as f64).max(1.0).powi(exponent);
+        let seconds = (self.initial_backoff.as_secs_f64() * factor).min(self.max_backoff.as_secs_f64());
+        Duration::try_from_secs_f64(seconds).unwrap_or(self.max_backoff)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(500),
+            backoff_multiplier: 2.0,
+            max_backoff: Duration::from_secs(5),
+            retry_llm_errors: false,
+        }
+    }
+}
+
+/// Record of an LLM call that needed repairs, more than one attempt, or failed outright
+#[derive(Debug, Clone, Serialize)]
+pub struct RepairRecord {
+    pub context: QueryContext,
+    /// Attempts made, including the first
+    pub attempts: u32,
+    /// The error from each failed attempt, in order
+    pub errors: Vec<String>,
+    /// Fixes the parser had to apply to the response that was finally used
+    pub json_repairs: Vec<JsonRepair>,
+    /// Whether a usable response was eventually produced
+    pub succeeded: bool,
+}
+
+impl RepairRecord {
+    /// Whether this call produced well-formed output on the first attempt
+    pub fn is_clean(&self) -> bool {
+        self.succeeded && self.attempts == 1 && self.json_repairs.is_empty()
+    }
+}
+
+/// Query the LLM and parse its response, re-querying with a repair prompt when parsing fails
+///
+/// Always returns the record of attempts alongside the result so callers can report on repairs.
+pub async fn query_json<T: DeserializeOwned>(
+    llm_client: &dyn LlmClient,
+    prompt_builder: &PromptBuilder,
+    policy: &RetryPolicy,
+    prompt: String,
+    context: &QueryContext,
+) -> (Result<T>, RepairRecord) {
+    let mut record = RepairRecord {
+        context: context.clone(),
+        attempts: 0,
+        errors: Vec::new(),
+        json_repairs: Vec::new(),
+        succeeded: false,
+    };
+
+    let mut current_prompt = prompt.clone();
+    loop {
+        record.attempts += 1;
+
+        let error = match llm_client
+            .query_with_context(current_prompt.clone(), Path::new("."), context)
+            .await
+        {
+            Ok(response) => match parser::extract_json_with_repairs::<T>(&response) {
+                Ok((value, json_repairs)) => {
+                    record.json_repairs = json_repairs;
+                    record.succeeded = true;
+                    return (Ok(value), record);
+                }
+                Err(e) => {
+                    current_prompt = match prompt_builder.build_repair_prompt(&prompt, &response, &repair_reason(&e)) {
+                        Ok(repair_prompt) => repair_prompt,
+                        Err(build_error) => {
+                            log::warn!("Failed to build repair prompt: {}", build_error);
+                            prompt.clone()
+                        }
+                    };
+                    e
+                }
+            },
+            Err(e) if policy.retry_llm_errors && !e.is_fatal() => e,
+            Err(e) => {
+                record.errors.push(e.to_string());
+                return (Err(e), record);
+            }
+        };
+
+        record.errors.push(error.to_string());
+
+        if record.attempts >= policy.max_attempts {
+            return (Err(error), record);
+        }
+
+        let backoff = policy.backoff_for(record.attempts);
+        log::warn!(
+            "LLM call for {:?} failed (attempt {}/{}), retrying in {:?}: {}",
+            context,
+            record.attempts,
+            policy.max_attempts,
+            backoff,
+            error
+        );
+        tokio::time::sleep(backoff).await;
+    }
+}
+
+/// What to tell the model was wrong with its output
+///
+/// Points at the underlying serde error rather than the whole message, which would repeat the response.
+fn repair_reason(error: &Error) -> String {
+    match error {
+        Error::ParseJson { source, .. } | Error::SchemaMismatch { source, .. } => source.to_string(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{MockLlmClient, MockMatcher, MockResponse};
+
+    /// Queries for a JSON value from a mock that fails once, then answers
+    async fn query_flaky(policy: &RetryPolicy) -> (Result<serde_json::Value>, RepairRecord) {
+        let mock = MockLlmClient::new().respond_sequence(
+            MockMatcher::Any,
+            vec![
+                MockResponse::Error("connection reset".to_string()),
+                MockResponse::Text(r#"{"ok": true}"#.to_string()),
+            ],
+        );
+        let prompts = PromptBuilder::new("data");
+        query_json(&mock, &prompts, policy, "Say ok".to_string(), &QueryContext::gm()).await
+    }
+
+    #[test]
+    fn backoff_grows_then_caps() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.backoff_for(1), Duration::from_millis(500));
+        assert_eq!(policy.backoff_for(2), Duration::from_secs(1));
+        assert_eq!(policy.backoff_for(5), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn backoff_never_overflows() {
+        assert_eq!(RetryPolicy::new(200).backoff_for(100), Duration::from_secs(5));
+        assert_eq!(RetryPolicy::default().backoff_for(u32::MAX), Duration::from_secs(5));
+
+        let steep = RetryPolicy::new(50).with_backoff(Duration::from_secs(1), 10.0);
+        assert_eq!(steep.backoff_for(25), Duration::from_secs(5));
+
+        let unbounded = steep.with_max_backoff(Duration::MAX);
+        assert!(unbounded.backoff_for(40) <= Duration::MAX);
+    }
+
+    #[tokio::test]
+    async fn llm_errors_are_not_retried_by_default() {
+        let (result, record) = query_flaky(&RetryPolicy::default()).await;
+
+        assert!(result.is_err());
+        assert_eq!(record.attempts, 1);
+        assert_eq!(record.errors.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn llm_errors_are_retried_when_asked() {
+        let policy = RetryPolicy::default().with_backoff(Duration::ZERO, 1.0).with_retry_llm_errors(true);
+        let (result, record) = query_flaky(&policy).await;
+
+        assert_eq!(result.unwrap(), serde_json::json!({"ok": true}));
+        assert_eq!(record.attempts, 2);
+        assert!(record.succeeded);
+    }
+}
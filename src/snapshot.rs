@@ -0,0 +1,249 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::clock::WorldClock;
+use crate::error::{Error, IoResultExt, Result};
+use crate::memory::MemorySystem;
+use crate::transcript::Transcript;
+use crate::types::GameState;
+
+/// Current snapshot file format version
+///
+/// Version 2 added the world clock to the game state.
+pub const SNAPSHOT_VERSION: u32 = 2;
+
+/// Everything needed to restore a running world: game state, memories and open transcripts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u32,
+    pub created_at: DateTime<Utc>,
+    /// NPC state (including pending `next_prompt`s), contracts and the turn counter
+    pub state: GameState,
+    /// Memory systems keyed by NPC name
+    pub memories: HashMap<String, MemorySystem>,
+    /// Transcripts of active contracts keyed by contract id
+    pub transcripts: HashMap<String, Transcript>,
+}
+
+impl Snapshot {
+    /// Creates a snapshot of the given state at the current format version
+    pub fn new(
+        state: GameState,
+        memories: HashMap<String, MemorySystem>,
+        transcripts: HashMap<String, Transcript>,
+    ) -> Self {
+        Self {
+            version: SNAPSHOT_VERSION,
+            created_at: Utc::now(),
+            state,
+            memories,
+            transcripts,
+        }
+    }
+
+    /// The turn this snapshot was taken at
+    pub fn turn(&self) -> u64 {
+        self.state.turn
+    }
+
+    /// Write the snapshot to a file, replacing it atomically
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).at(parent)?;
+        }
+
+        // Write to a temporary file first so a crash never leaves a half-written snapshot
+        let tmp_path = path.with_extension("json.tmp");
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(&tmp_path, json).at(&tmp_path)?;
+        fs::rename(&tmp_path, path).at(path)?;
+
+        Ok(())
+    }
+
+    /// Read a snapshot from a file, checking that its version is supported
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path).at(path)?;
+        let value: Value = serde_json::from_str(&content)
+            .map_err(|e| Error::invalid(format!("Snapshot {:?} is not valid JSON: {}", path, e)))?;
+
+        let version = value
+            .get("version")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| Error::invalid(format!("Snapshot {:?} has no version field", path)))?;
+
+        if version == 0 || version > SNAPSHOT_VERSION as u64 {
+            return Err(Error::invalid(format!(
+                "Snapshot {:?} has unsupported version {} (this build supports up to {})",
+                path,
+                version,
+                SNAPSHOT_VERSION
+            )));
+        }
+
+        let mut snapshot: Snapshot = serde_json::from_value(value)
+            .map_err(|e| Error::invalid(format!("Snapshot {:?} has an invalid format: {}", path, e)))?;
+
+        if version < 2 {
+            // Older worlds had no clock; start it where a new game would have and replay the turns
+            let mut clock = WorldClock::default();
+            clock.advance_turns(snapshot.state.turn);
+            snapshot.state.clock = clock;
+            snapshot.version = SNAPSHOT_VERSION;
+            log::info!("Upgraded snapshot {:?} from version {}, clock set to {}", path, version, clock);
+        }
+
+        Ok(snapshot)
+    }
+}
+
+/// A snapshot file found in the automatic snapshot directory
+#[derive(Debug, Clone)]
+pub struct SnapshotInfo {
+    pub turn: u64,
+    pub path: PathBuf,
+}
+
+/// Manages the automatic per-turn snapshots stored in `data/snapshots`
+pub struct SnapshotStore {
+    snapshots_dir: PathBuf,
+}
+
+impl SnapshotStore {
+    pub fn new(data_path: impl AsRef<Path>) -> Self {
+        Self {
+            snapshots_dir: data_path.as_ref().join("snapshots"),
+        }
+    }
+
+    /// Path of the automatic snapshot for a turn
+    pub fn path_for(&self, turn: u64) -> PathBuf {
+        self.snapshots_dir.join(format!("turn_{:06}.json", turn))
+    }
+
+    /// List all automatic snapshots, oldest first
+    pub fn list(&self) -> Result<Vec<SnapshotInfo>> {
+        if !self.snapshots_dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut snapshots = Vec::new();
+        for entry in fs::read_dir(&self.snapshots_dir).at(&self.snapshots_dir)? {
+            let path = entry.at(&self.snapshots_dir)?.path();
+            let turn = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .and_then(|n| n.strip_prefix("turn_"))
+                .and_then(|n| n.strip_suffix(".json"))
+                .and_then(|n| n.parse::<u64>().ok());
+
+            if let Some(turn) = turn {
+                snapshots.push(SnapshotInfo { turn, path });
+            }
+        }
+
+        snapshots.sort_by_key(|s| s.turn);
+        Ok(snapshots)
+    }
+
+    /// Delete the oldest snapshots so that at most `keep` remain
+    pub fn prune(&self, keep: usize) -> Result<()> {
+        let snapshots = self.list()?;
+        let excess = snapshots.len().saturating_sub(keep);
+
+        for info in snapshots.into_iter().take(excess) {
+            log::debug!("Removing old snapshot {:?}", info.path);
+            fs::remove_file(&info.path).at(&info.path)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Npc;
+    use tempfile::TempDir;
+
+    fn snapshot(turn: u64) -> Snapshot {
+        let mut state = GameState {
+            npcs: HashMap::new(),
+            contracts: HashMap::new(),
+            turn,
+            clock: WorldClock::new(2, 14, 30),
+        };
+        let mut alice = Npc::new("alice", "tavern", "sitting at the bar");
+        alice.next_prompt = Some("Bob waves. What do you do?".to_string());
+        state.npcs.insert("alice".to_string(), alice);
+        let transcript = Transcript::new("talk", vec!["alice".to_string(), "bob".to_string()]);
+        Snapshot::new(state, HashMap::new(), HashMap::from([("talk".to_string(), transcript)]))
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("nested/save.json");
+        snapshot(7).save(&path).unwrap();
+
+        let loaded = Snapshot::load(&path).unwrap();
+        assert_eq!(loaded.version, SNAPSHOT_VERSION);
+        assert_eq!(loaded.turn(), 7);
+        assert_eq!(loaded.state.clock, WorldClock::new(2, 14, 30));
+        assert_eq!(loaded.state.npcs["alice"].next_prompt.as_deref(), Some("Bob waves. What do you do?"));
+        assert_eq!(loaded.transcripts["talk"].participants, ["alice", "bob"]);
+        assert!(!path.with_extension("json.tmp").exists());
+    }
+
+    #[test]
+    fn version_1_snapshots_get_a_clock_for_their_turn() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("old.json");
+        let mut value = serde_json::to_value(snapshot(5)).unwrap();
+        value["version"] = 1.into();
+        value["state"].as_object_mut().unwrap().remove("clock");
+        fs::write(&path, value.to_string()).unwrap();
+
+        let loaded = Snapshot::load(&path).unwrap();
+        assert_eq!(loaded.version, SNAPSHOT_VERSION);
+        // Five half-hour turns from the default 08:00 start
+        assert_eq!(loaded.state.clock.to_string(), "day 1, 10:30");
+    }
+
+    #[test]
+    fn unsupported_versions_are_rejected() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("future.json");
+        for version in [0, SNAPSHOT_VERSION + 1] {
+            let mut value = serde_json::to_value(snapshot(1)).unwrap();
+            value["version"] = version.into();
+            fs::write(&path, value.to_string()).unwrap();
+
+            let error = Snapshot::load(&path).unwrap_err().to_string();
+            assert!(error.contains("unsupported version"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn store_lists_oldest_first_and_prunes_the_oldest() {
+        let dir = TempDir::new().unwrap();
+        let store = SnapshotStore::new(dir.path());
+        assert!(store.list().unwrap().is_empty());
+        for turn in [3, 1, 12, 2] {
+            snapshot(turn).save(store.path_for(turn)).unwrap();
+        }
+        fs::write(dir.path().join("snapshots/notes.txt"), "not a snapshot").unwrap();
+
+        let turns = |store: &SnapshotStore| store.list().unwrap().iter().map(|s| s.turn).collect::<Vec<_>>();
+        assert_eq!(turns(&store), [1, 2, 3, 12]);
+
+        store.prune(2).unwrap();
+        assert_eq!(turns(&store), [3, 12]);
+    }
+}
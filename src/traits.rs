@@ -1,8 +1,9 @@
-use anyhow::Result;
+use crate::error::Result;
 use crate::types::{Intent, Npc};
 use crate::memory::{MemorySystem, MemoryUpdate};
 
 /// Trait for implementing NPC behavior and decision-making
+#[allow(async_fn_in_trait)]
 pub trait NpcBehavior: Send + Sync {
     /// Determines what action this NPC wants to take given the current context
     async fn decide_action(&self, npc: &Npc, context: &dyn Context) -> Result<Intent>;
@@ -27,6 +28,7 @@ pub trait Context: Send + Sync {
 }
 
 /// Trait for managing NPC memories
+#[allow(async_fn_in_trait)]
 pub trait MemoryManager: Send + Sync {
     /// Updates memories based on new events
     async fn update_memories(&mut self, update: MemoryUpdate) -> Result<()>;
@@ -42,6 +44,7 @@ pub trait MemoryManager: Send + Sync {
 }
 
 /// Trait for loading and saving NPC data
+#[allow(async_fn_in_trait)]
 pub trait NpcStorage: Send + Sync {
     /// Loads an NPC from storage
     async fn load_npc(&self, name: &str) -> Result<Npc>;
@@ -82,7 +85,14 @@ impl PerceptionResult {
     }
 }
 
+impl Default for PerceptionResult {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Trait for social interactions between NPCs
+#[allow(async_fn_in_trait)]
 pub trait SocialInteraction: Send + Sync {
     /// Initiates a social interaction with another NPC
     async fn initiate_interaction(&self, initiator: &Npc, target: &Npc, interaction_type: &str) -> Result<InteractionResult>;
@@ -0,0 +1,237 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, IoResultExt, Result};
+use crate::types::TranscriptEntry;
+
+/// A single exchange recorded in a contract transcript
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptRecord {
+    /// Position of this exchange within the transcript, starting at 1
+    pub sequence: usize,
+    /// The contract action the GM reported alongside this exchange ("create", "update", "end")
+    pub action: String,
+    pub entry: TranscriptEntry,
+}
+
+/// The ordered history of everything that happened within a contract
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Transcript {
+    pub contract_id: String,
+    pub participants: Vec<String>,
+    pub entries: Vec<TranscriptRecord>,
+}
+
+impl Transcript {
+    /// Creates an empty transcript for the given contract
+    pub fn new(contract_id: impl Into<String>, participants: Vec<String>) -> Self {
+        Self {
+            contract_id: contract_id.into(),
+            participants,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Appends an exchange to the end of the transcript
+    pub fn push(&mut self, action: impl Into<String>, entry: TranscriptEntry) {
+        self.entries.push(TranscriptRecord {
+            sequence: self.entries.len() + 1,
+            action: action.into(),
+            entry,
+        });
+    }
+
+    /// Renders the transcript as readable dialogue for use in prompts
+    pub fn to_dialogue(&self) -> String {
+        let mut text = format!("Interaction between {}", self.participants.join(", "));
+
+        if self.entries.is_empty() {
+            text.push_str(" has just begun.\n");
+            return text;
+        }
+
+        text.push_str(":\n");
+        for record in &self.entries {
+            text.push_str(&format!("\n{}. {}\n", record.sequence, record.entry.reality));
+
+            // Keep speakers in a stable order so prompts don't shuffle between turns
+            let mut speakers: Vec<_> = record.entry.details.iter().collect();
+            speakers.sort_by(|a, b| a.0.cmp(b.0));
+
+            for (name, details) in speakers {
+                match &details.dialogue {
+                    Some(dialogue) => text.push_str(&format!(
+                        "   - {} ({}): \"{}\"\n",
+                        name, details.action, dialogue
+                    )),
+                    None => text.push_str(&format!("   - {} ({})\n", name, details.action)),
+                }
+            }
+        }
+
+        text
+    }
+}
+
+/// Reads and appends contract transcripts stored as JSON files in `data/contracts`
+pub struct TranscriptStore {
+    contracts_dir: PathBuf,
+}
+
+impl TranscriptStore {
+    pub fn new(data_path: impl AsRef<Path>) -> Self {
+        Self {
+            contracts_dir: data_path.as_ref().join("contracts"),
+        }
+    }
+
+    /// Path of the transcript file for a contract
+    ///
+    /// Ids that aren't safe as file names get a hash of the original id appended, so
+    /// `a/b` and `a_b` don't end up sharing a transcript.
+    pub fn path_for(&self, contract_id: &str) -> PathBuf {
+        // Contract ids come from the LLM, so keep them from escaping the contracts directory
+        let mut file_name: String = contract_id
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+            .collect();
+        if file_name != contract_id {
+            // `.` never survives sanitising, so these can't clash with ids that were already safe
+            file_name.push_str(&format!(".{:016x}", fnv1a(contract_id)));
+        }
+        self.contracts_dir.join(format!("{}.json", file_name))
+    }
+
+    /// Load the transcript for a contract
+    pub fn load(&self, contract_id: &str) -> Result<Transcript> {
+        let path = self.path_for(contract_id);
+        let content = fs::read_to_string(&path).at(&path)?;
+        serde_json::from_str(&content)
+            .map_err(|e| Error::invalid(format!("Failed to parse transcript {:?}: {}", path, e)))
+    }
+
+    /// Load the transcript for a contract, or start a new one if none has been written yet
+    pub fn load_or_new(&self, contract_id: &str, participants: &[String]) -> Result<Transcript> {
+        if self.path_for(contract_id).exists() {
+            self.load(contract_id)
+        } else {
+            Ok(Transcript::new(contract_id, participants.to_vec()))
+        }
+    }
+
+    /// Write a transcript to disk, replacing any previous version
+    pub fn save(&self, transcript: &Transcript) -> Result<()> {
+        fs::create_dir_all(&self.contracts_dir).at(&self.contracts_dir)?;
+        let path = self.path_for(&transcript.contract_id);
+        let json = serde_json::to_string_pretty(transcript)?;
+        fs::write(&path, json).at(&path)
+    }
+
+    /// Append an exchange to a contract's transcript, creating the file if needed
+    pub fn append(
+        &self,
+        contract_id: &str,
+        participants: &[String],
+        action: &str,
+        entry: TranscriptEntry,
+    ) -> Result<Transcript> {
+        let mut transcript = self.load_or_new(contract_id, participants)?;
+
+        // The GM may add or drop participants as the interaction evolves
+        if !participants.is_empty() {
+            transcript.participants = participants.to_vec();
+        }
+
+        transcript.push(action, entry);
+        self.save(&transcript)?;
+
+        Ok(transcript)
+    }
+}
+
+/// 64-bit FNV-1a, stable across runs and Rust versions unlike `DefaultHasher`
+fn fnv1a(text: &str) -> u64 {
+    text.bytes()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01B3))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::NpcAction;
+    use std::collections::HashMap;
+
+    fn entry(reality: &str, details: &[(&str, &str, Option<&str>)]) -> TranscriptEntry {
+        TranscriptEntry {
+            reality: reality.to_string(),
+            details: details
+                .iter()
+                .map(|(name, action, dialogue)| {
+                    let action = NpcAction { action: action.to_string(), dialogue: dialogue.map(str::to_string) };
+                    (name.to_string(), action)
+                })
+                .collect::<HashMap<_, _>>(),
+        }
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn path_for_keeps_safe_ids() {
+        let store = TranscriptStore::new("data");
+        assert_eq!(store.path_for("conv_alice-bob_1"), Path::new("data/contracts/conv_alice-bob_1.json"));
+    }
+
+    #[test]
+    fn path_for_never_maps_two_ids_to_one_file() {
+        let store = TranscriptStore::new("data");
+        let ids = ["a_b", "a/b", "a b", "a.b", "../a_b", "a_b.json"];
+        let paths: std::collections::HashSet<PathBuf> = ids.iter().map(|id| store.path_for(id)).collect();
+        assert_eq!(paths.len(), ids.len());
+
+        for path in &paths {
+            assert_eq!(path.parent(), Some(Path::new("data/contracts")));
+        }
+        assert_eq!(store.path_for("a/b"), store.path_for("a/b"));
+    }
+
+    #[test]
+    fn append_numbers_entries_and_follows_participants() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = TranscriptStore::new(dir.path());
+
+        store.append("talk", &names(&["alice", "bob"]), "create", entry("Alice waves.", &[])).unwrap();
+        store.append("talk", &[], "update", entry("Bob waves back.", &[])).unwrap();
+        let transcript = store
+            .append("talk", &names(&["alice", "bob", "carol"]), "end", entry("Carol joins.", &[]))
+            .unwrap();
+
+        assert_eq!(transcript.participants, ["alice", "bob", "carol"]);
+        let entries: Vec<_> = transcript.entries.iter().map(|r| (r.sequence, r.action.as_str())).collect();
+        assert_eq!(entries, [(1, "create"), (2, "update"), (3, "end")]);
+
+        let loaded = store.load("talk").unwrap();
+        assert_eq!(loaded.entries.len(), 3);
+        assert_eq!(loaded.entries[1].entry.reality, "Bob waves back.");
+    }
+
+    #[test]
+    fn empty_transcript_has_just_begun() {
+        let transcript = Transcript::new("talk", names(&["alice", "bob"]));
+        assert_eq!(transcript.to_dialogue(), "Interaction between alice, bob has just begun.\n");
+    }
+
+    #[test]
+    fn dialogue_lists_speakers_in_name_order() {
+        let mut transcript = Transcript::new("talk", names(&["bob", "alice"]));
+        transcript.push("create", entry("They meet.", &[("bob", "nods", None), ("alice", "waves", Some("Hi!"))]));
+
+        assert_eq!(
+            transcript.to_dialogue(),
+            "Interaction between bob, alice:\n\n1. They meet.\n   - alice (waves): \"Hi!\"\n   - bob (nods)\n"
+        );
+    }
+}
@@ -1,5 +1,8 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::clock::WorldClock;
+use crate::world::Location;
 
 /// Represents a Non-Player Character with location and activity state
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -10,6 +13,18 @@ pub struct Npc {
     pub folder_path: String,
     pub active_contract: Option<String>,
     pub next_prompt: Option<String>,
+    /// Daily routine declared in the NPC's state.json
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub schedule: Vec<ScheduleEntry>,
+    /// Free-form attributes declared in the NPC's state.json (occupation, age, ...)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub attributes: HashMap<String, serde_json::Value>,
+    /// Whether this character is played by the host game rather than the LLM
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub player_controlled: bool,
+    /// The journey the NPC is on, if they are travelling between locations
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub travel: Option<Travel>,
 }
 
 impl Npc {
@@ -23,6 +38,157 @@ impl Npc {
             activity: activity.into(),
             active_contract: None,
             next_prompt: None,
+            schedule: Vec::new(),
+            attributes: HashMap::new(),
+            player_controlled: false,
+            travel: None,
+        }
+    }
+
+    /// Where the NPC's routine puts them at the given time, the first matching block winning
+    pub fn scheduled(&self, clock: &WorldClock) -> Option<&ScheduleEntry> {
+        self.schedule.iter().find(|entry| entry.covers(clock.hour))
+    }
+
+    /// Spend a turn travelling, moving to the next location on the route once its leg is done
+    ///
+    /// Returns the location reached, if any. Travel ends on arrival at the destination.
+    pub fn advance_travel(&mut self) -> Option<String> {
+        let travel = self.travel.as_mut()?;
+        let Some(leg) = travel.route.first_mut() else {
+            self.travel = None;
+            return None;
+        };
+
+        leg.turns = leg.turns.saturating_sub(1);
+        if leg.turns > 0 {
+            return None;
+        }
+
+        let reached = travel.route.remove(0).to;
+        if travel.route.is_empty() {
+            self.travel = None;
+        }
+        self.location = reached.clone();
+        Some(reached)
+    }
+}
+
+/// A journey between locations that aren't next to each other, or take several turns to cross
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Travel {
+    pub destination: String,
+    /// Legs still to travel, ending at the destination; the first is the one in progress
+    pub route: Vec<Leg>,
+}
+
+impl Travel {
+    /// Turns until the destination is reached
+    pub fn turns_left(&self) -> u32 {
+        self.route.iter().map(|leg| leg.turns).sum()
+    }
+}
+
+/// One step of a journey, between neighbouring locations
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Leg {
+    pub to: String,
+    /// Turns left until the location is reached
+    pub turns: u32,
+}
+
+/// Starting state declared in an NPC's `state.json`
+///
+/// Every field is optional in the file; missing fields fall back to the defaults.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NpcStateFile {
+    pub location: String,
+    pub activity: String,
+    pub schedule: Vec<ScheduleEntry>,
+    pub attributes: HashMap<String, serde_json::Value>,
+    /// A journey in progress when the state was saved
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub travel: Option<Travel>,
+}
+
+impl NpcStateFile {
+    /// Check the file for values that deserialize fine but make no sense
+    pub fn validate(&self) -> Result<(), String> {
+        if self.location.trim().is_empty() {
+            return Err("location must not be empty".to_string());
+        }
+        if self.activity.trim().is_empty() {
+            return Err("activity must not be empty".to_string());
+        }
+        for (i, entry) in self.schedule.iter().enumerate() {
+            entry.validate().map_err(|e| format!("schedule[{}]: {}", i, e))?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for NpcStateFile {
+    fn default() -> Self {
+        Self {
+            location: "start".to_string(),
+            activity: "idle".to_string(),
+            schedule: Vec::new(),
+            attributes: HashMap::new(),
+            travel: None,
+        }
+    }
+}
+
+impl From<&Npc> for NpcStateFile {
+    fn from(npc: &Npc) -> Self {
+        Self {
+            location: npc.location.clone(),
+            activity: npc.activity.clone(),
+            schedule: npc.schedule.clone(),
+            attributes: npc.attributes.clone(),
+            travel: npc.travel.clone(),
+        }
+    }
+}
+
+/// A block of an NPC's daily routine, e.g. at the forge from 8 to 18
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScheduleEntry {
+    /// Hour the block starts, 0-23
+    pub start_hour: u8,
+    /// Hour the block ends (exclusive), 1-24; may be less than start_hour to wrap past midnight
+    pub end_hour: u8,
+    pub location: String,
+    pub activity: String,
+}
+
+impl ScheduleEntry {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.start_hour > 23 {
+            return Err(format!("start_hour {} is out of range (0-23)", self.start_hour));
+        }
+        if self.end_hour > 24 {
+            return Err(format!("end_hour {} is out of range (0-24)", self.end_hour));
+        }
+        if self.start_hour == self.end_hour {
+            return Err(format!("start_hour and end_hour are both {}", self.start_hour));
+        }
+        if self.location.trim().is_empty() {
+            return Err("location must not be empty".to_string());
+        }
+        Ok(())
+    }
+
+    /// Whether the block includes the given hour of the day
+    pub fn covers(&self, hour: u32) -> bool {
+        let (start, end) = (self.start_hour as u32, self.end_hour as u32);
+        if start < end {
+            (start..end).contains(&hour)
+        } else {
+            // Wraps past midnight
+            hour >= start || hour < end
         }
     }
 }
@@ -43,8 +209,19 @@ pub struct NpcAction {
     pub dialogue: Option<String>,
 }
 
+/// An NPC's in-character answer to something said to them directly
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DialogueReply {
+    #[serde(default)]
+    pub thought: String,
+    #[serde(default)]
+    pub action: String,
+    /// What the NPC says, or None if they stay silent
+    pub dialogue: Option<String>,
+}
+
 /// A contract between NPCs for extended interactions
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Contract {
     pub id: String,
     pub participants: Vec<String>,
@@ -52,10 +229,16 @@ pub struct Contract {
 }
 
 /// The current state of the game world
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GameState {
     pub npcs: HashMap<String, Npc>,
     pub contracts: HashMap<String, Contract>,
+    /// Number of turns executed so far
+    #[serde(default)]
+    pub turn: u64,
+    /// In-game time
+    #[serde(default)]
+    pub clock: WorldClock,
 }
 
 /// Data sent to the GM for resolution
@@ -63,25 +246,42 @@ pub struct GameState {
 pub struct GmInput {
     pub current_state: CurrentState,
     pub intents: Vec<Intent>,
+    /// Every location NPCs can go to, when the game has a world map
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locations: Option<BTreeMap<String, Location>>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct CurrentState {
+    /// In-game time, e.g. "day 3, 06:30 (dawn)"
+    pub time: String,
+    pub minutes_per_turn: u32,
     pub npcs: HashMap<String, Npc>,
     pub active_contracts: HashMap<String, Contract>,
 }
 
 /// Response from the GM about what actually happened
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GmResponse {
     pub reality: String,
     pub state_changes: Vec<StateChange>,
     pub contracts: Vec<ContractUpdate>,
     pub next_prompts: HashMap<String, String>,
+    /// What NPCs saw when resolution was split by location, keyed by NPC; `reality` then
+    /// joins every partition's account and only NPCs missing here saw all of it
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub local_realities: HashMap<String, String>,
+}
+
+impl GmResponse {
+    /// The account of the turn an NPC witnessed
+    pub fn reality_for(&self, npc: &str) -> &str {
+        self.local_realities.get(npc).unwrap_or(&self.reality)
+    }
 }
 
 /// A change to an NPC's state
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StateChange {
     pub npc: String,
     pub location: String,
@@ -89,7 +289,7 @@ pub struct StateChange {
 }
 
 /// Updates to contracts
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContractUpdate {
     pub id: String,
     pub participants: Vec<String>,
@@ -105,10 +305,49 @@ pub struct TranscriptEntry {
 }
 
 /// Input for updating an NPC's memories
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MemoryUpdateInput {
     pub npc_name: String,
     pub intent: Intent,
     pub reality: String,
     pub other_npcs_present: Vec<String>,
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(start_hour: u8, end_hour: u8, location: &str) -> ScheduleEntry {
+        ScheduleEntry { start_hour, end_hour, location: location.to_string(), activity: "working".to_string() }
+    }
+
+    #[test]
+    fn blocks_cover_their_hours_and_wrap_past_midnight() {
+        let day = block(8, 18, "forge");
+        assert!(day.covers(8) && day.covers(17));
+        assert!(!day.covers(18) && !day.covers(7));
+
+        let night = block(22, 6, "watchtower");
+        assert!(night.covers(23) && night.covers(0) && night.covers(5));
+        assert!(!night.covers(6) && !night.covers(21));
+    }
+
+    #[test]
+    fn invalid_blocks_are_rejected() {
+        assert!(block(8, 18, "forge").validate().is_ok());
+        assert!(block(24, 2, "forge").validate().is_err());
+        assert!(block(8, 25, "forge").validate().is_err());
+        assert!(block(8, 8, "forge").validate().is_err());
+        assert!(block(8, 18, " ").validate().is_err());
+    }
+
+    #[test]
+    fn the_first_matching_block_wins() {
+        let mut npc = Npc::new("greta", "home", "sleeping");
+        npc.schedule = vec![block(8, 12, "market"), block(6, 18, "forge")];
+
+        let at = |hour| npc.scheduled(&WorldClock::new(1, hour, 0)).map(|entry| entry.location.as_str());
+        assert_eq!(at(7), Some("forge"));
+        assert_eq!(at(9), Some("market"));
+        assert_eq!(at(20), None);
+    }
+}
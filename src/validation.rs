@@ -0,0 +1,736 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+use crate::types::{GameState, GmResponse, Intent};
+use crate::world::World;
+
+/// Prompt given to NPCs the GM forgot to write a next prompt for
+const DEFAULT_NEXT_PROMPT: &str = "What do you do next?";
+
+/// A problem found in a GM response
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Diagnostic {
+    /// A state change names an NPC that doesn't exist
+    UnknownNpcInStateChange { npc: String },
+    /// A contract lists a participant that doesn't exist
+    UnknownNpcInContract { contract: String, npc: String },
+    /// A next prompt is addressed to an NPC that doesn't exist
+    UnknownNpcInNextPrompts { npc: String },
+    /// A contract is updated or ended but was never created
+    UnknownContract { contract: String, action: String },
+    /// A contract action other than "create", "update" or "end"
+    UnknownContractAction { contract: String, action: String },
+    /// A contract is created with an id that is already in use
+    DuplicateContractId { contract: String },
+    /// An NPC would end up in more than one contract at once
+    NpcInMultipleContracts { npc: String, contracts: Vec<String> },
+    /// A contract's participants would not be at the same location
+    ParticipantsNotColocated { contract: String, locations: BTreeMap<String, String> },
+    /// An NPC acted this turn but gets no prompt for the next one
+    MissingNextPrompt { npc: String },
+    /// A state change sends an NPC to a location that isn't on the world map
+    UnknownLocation { npc: String, location: String },
+    /// A state change sends an NPC somewhere there is no way to get to from where they are
+    UnreachableLocation { npc: String, from: String, to: String },
+    /// Several GM partitions change the same NPC; only one change is kept
+    ConflictingStateChanges { npc: String },
+    /// Several GM partitions act on the same contract; only one is kept
+    ConflictingContracts { contract: String },
+    /// Several GM partitions write a next prompt for the same NPC; only one is kept
+    ConflictingNextPrompts { npc: String },
+}
+
+impl Diagnostic {
+    /// Whether `auto_fix` knows how to repair this problem
+    pub fn is_fixable(&self) -> bool {
+        !matches!(self, Diagnostic::ParticipantsNotColocated { .. })
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Diagnostic::UnknownNpcInStateChange { npc } => {
+                write!(f, "state change for unknown NPC '{}'", npc)
+            }
+            Diagnostic::UnknownNpcInContract { contract, npc } => {
+                write!(f, "contract '{}' includes unknown NPC '{}'", contract, npc)
+            }
+            Diagnostic::UnknownNpcInNextPrompts { npc } => {
+                write!(f, "next prompt for unknown NPC '{}'", npc)
+            }
+            Diagnostic::UnknownContract { contract, action } => {
+                write!(f, "'{}' on contract '{}' which does not exist", action, contract)
+            }
+            Diagnostic::UnknownContractAction { contract, action } => {
+                write!(f, "unknown action '{}' on contract '{}'", action, contract)
+            }
+            Diagnostic::DuplicateContractId { contract } => {
+                write!(f, "contract '{}' is created but already exists", contract)
+            }
+            Diagnostic::NpcInMultipleContracts { npc, contracts } => {
+                write!(f, "'{}' would be in several contracts: {}", npc, contracts.join(", "))
+            }
+            Diagnostic::ParticipantsNotColocated { contract, locations } => {
+                let where_: Vec<_> = locations.iter().map(|(npc, loc)| format!("{} at {}", npc, loc)).collect();
+                write!(f, "participants of contract '{}' are apart: {}", contract, where_.join(", "))
+            }
+            Diagnostic::MissingNextPrompt { npc } => {
+                write!(f, "'{}' acted but has no next prompt", npc)
+            }
+            Diagnostic::UnknownLocation { npc, location } => {
+                write!(f, "'{}' moves to unknown location '{}'", npc, location)
+            }
+            Diagnostic::UnreachableLocation { npc, from, to } => {
+                write!(f, "'{}' can't get from '{}' to '{}'", npc, from, to)
+            }
+            Diagnostic::ConflictingStateChanges { npc } => {
+                write!(f, "several GM partitions change the state of '{}'", npc)
+            }
+            Diagnostic::ConflictingContracts { contract } => {
+                write!(f, "several GM partitions act on contract '{}'", contract)
+            }
+            Diagnostic::ConflictingNextPrompts { npc } => {
+                write!(f, "several GM partitions write a next prompt for '{}'", npc)
+            }
+        }
+    }
+}
+
+/// What the engine does with a GM response that has problems
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ValidationPolicy {
+    /// Fail the resolution with an error listing the problems
+    Reject,
+    /// Repair what can be repaired and warn about the rest
+    AutoFix,
+    /// Apply the response as-is and log the problems, except that moves off the world map
+    /// are always undone (see `fix_locations`)
+    #[default]
+    Warn,
+}
+
+/// Check a GM response against the state it will be applied to
+///
+/// `intents` are the intents the GM was resolving; every NPC that acted should get a next prompt.
+pub fn validate_gm_response(response: &GmResponse, state: &GameState, intents: &[Intent]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    for change in &response.state_changes {
+        if !state.npcs.contains_key(&change.npc) {
+            diagnostics.push(Diagnostic::UnknownNpcInStateChange { npc: change.npc.clone() });
+        }
+    }
+
+    let mut created = HashSet::new();
+    for update in &response.contracts {
+        for participant in &update.participants {
+            if !state.npcs.contains_key(participant) {
+                diagnostics.push(Diagnostic::UnknownNpcInContract {
+                    contract: update.id.clone(),
+                    npc: participant.clone(),
+                });
+            }
+        }
+
+        let exists = state.contracts.contains_key(&update.id) || created.contains(&update.id);
+        match update.action.as_str() {
+            "create" if exists => {
+                diagnostics.push(Diagnostic::DuplicateContractId { contract: update.id.clone() });
+            }
+            "create" => {
+                created.insert(update.id.clone());
+            }
+            "update" | "end" if !exists => diagnostics.push(Diagnostic::UnknownContract {
+                contract: update.id.clone(),
+                action: update.action.clone(),
+            }),
+            "update" | "end" => {}
+            _ => diagnostics.push(Diagnostic::UnknownContractAction {
+                contract: update.id.clone(),
+                action: update.action.clone(),
+            }),
+        }
+    }
+
+    let mut prompted: Vec<_> = response.next_prompts.keys().collect();
+    prompted.sort();
+    for npc in prompted {
+        if !state.npcs.contains_key(npc) {
+            diagnostics.push(Diagnostic::UnknownNpcInNextPrompts { npc: npc.clone() });
+        }
+    }
+
+    let contracts = resulting_contracts(response, state);
+
+    let mut memberships: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for (id, participants) in &contracts {
+        for participant in participants {
+            memberships.entry(participant.as_str()).or_default().push(id.clone());
+        }
+    }
+    for (npc, contract_ids) in memberships {
+        if contract_ids.len() > 1 && state.npcs.contains_key(npc) {
+            diagnostics.push(Diagnostic::NpcInMultipleContracts {
+                npc: npc.to_string(),
+                contracts: contract_ids,
+            });
+        }
+    }
+
+    let locations = resulting_locations(response, state);
+    for (id, participants) in &contracts {
+        let placed: BTreeMap<String, String> = participants
+            .iter()
+            .filter_map(|p| locations.get(p.as_str()).map(|loc| (p.clone(), loc.to_string())))
+            .collect();
+        let distinct: HashSet<_> = placed.values().collect();
+        if distinct.len() > 1 {
+            diagnostics.push(Diagnostic::ParticipantsNotColocated {
+                contract: id.clone(),
+                locations: placed,
+            });
+        }
+    }
+
+    for intent in intents {
+        if state.npcs.contains_key(&intent.npc) && !response.next_prompts.contains_key(&intent.npc) {
+            diagnostics.push(Diagnostic::MissingNextPrompt { npc: intent.npc.clone() });
+        }
+    }
+
+    diagnostics
+}
+
+/// Repair a GM response in place, returning every problem found (fixed or not)
+///
+/// Unknown NPCs are dropped, updates to unknown contracts become creates, duplicate
+/// creates become updates, NPCs are kept out of a second contract, and NPCs who acted
+/// without a next prompt get a generic one. Participants who are apart can't be fixed
+/// and are only reported.
+pub fn auto_fix(response: &mut GmResponse, state: &GameState, intents: &[Intent]) -> Vec<Diagnostic> {
+    let diagnostics = validate_gm_response(response, state, intents);
+    if diagnostics.is_empty() {
+        return diagnostics;
+    }
+
+    response.state_changes.retain(|change| state.npcs.contains_key(&change.npc));
+    response.next_prompts.retain(|npc, _| state.npcs.contains_key(npc));
+
+    let mut known_ids: HashSet<String> = state.contracts.keys().cloned().collect();
+    // Contracts each NPC is already in and will still be in after this response
+    let ended: HashSet<&str> = response
+        .contracts
+        .iter()
+        .filter(|u| u.action == "end")
+        .map(|u| u.id.as_str())
+        .collect();
+    let mut member_of: HashMap<String, String> = state
+        .contracts
+        .values()
+        .filter(|c| !ended.contains(c.id.as_str()))
+        .flat_map(|c| c.participants.iter().map(move |p| (p.clone(), c.id.clone())))
+        .collect();
+
+    let mut fixed = Vec::with_capacity(response.contracts.len());
+    for mut update in std::mem::take(&mut response.contracts) {
+        update.participants.retain(|p| state.npcs.contains_key(p));
+
+        let exists = known_ids.contains(&update.id);
+        match update.action.as_str() {
+            "create" if exists => update.action = "update".to_string(),
+            "update" if !exists => update.action = "create".to_string(),
+            "end" if !exists => continue,
+            "create" | "update" | "end" => {}
+            _ => continue,
+        }
+
+        if update.action != "end" {
+            // Keep NPCs out of a second contract; their existing one takes precedence
+            update.participants.retain(|p| match member_of.get(p) {
+                Some(current) => current == &update.id,
+                None => true,
+            });
+            if update.action == "create" && update.participants.is_empty() {
+                continue;
+            }
+            for participant in &update.participants {
+                member_of.insert(participant.clone(), update.id.clone());
+            }
+        }
+
+        known_ids.insert(update.id.clone());
+        fixed.push(update);
+    }
+    response.contracts = fixed;
+
+    for intent in intents {
+        if state.npcs.contains_key(&intent.npc) {
+            response
+                .next_prompts
+                .entry(intent.npc.clone())
+                .or_insert_with(|| DEFAULT_NEXT_PROMPT.to_string());
+        }
+    }
+
+    diagnostics
+}
+
+/// Check that every move in a GM response goes somewhere on the world map NPCs can get to
+///
+/// Moves to places that aren't next door are fine; the engine turns them into travel.
+/// NPCs standing somewhere off the map may move onto it anywhere.
+pub fn validate_locations(response: &GmResponse, state: &GameState, world: &World) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    for change in &response.state_changes {
+        let Some(npc) = state.npcs.get(&change.npc) else {
+            continue;
+        };
+        if npc.location == change.location {
+            continue;
+        }
+
+        if !world.contains(&change.location) {
+            diagnostics.push(Diagnostic::UnknownLocation {
+                npc: change.npc.clone(),
+                location: change.location.clone(),
+            });
+        } else if world.contains(&npc.location) && world.route(&npc.location, &change.location).is_none() {
+            diagnostics.push(Diagnostic::UnreachableLocation {
+                npc: change.npc.clone(),
+                from: npc.location.clone(),
+                to: change.location.clone(),
+            });
+        }
+    }
+
+    diagnostics
+}
+
+/// Keep NPCs where they are rather than move them somewhere unknown or unreachable,
+/// returning the problems found
+pub fn fix_locations(response: &mut GmResponse, state: &GameState, world: &World) -> Vec<Diagnostic> {
+    let diagnostics = validate_locations(response, state, world);
+
+    for diagnostic in &diagnostics {
+        let npc = match diagnostic {
+            Diagnostic::UnknownLocation { npc, .. } | Diagnostic::UnreachableLocation { npc, .. } => npc,
+            _ => continue,
+        };
+        let Some(current) = state.npcs.get(npc) else {
+            continue;
+        };
+        for change in response.state_changes.iter_mut().filter(|c| &c.npc == npc) {
+            change.location = current.location.clone();
+        }
+    }
+
+    diagnostics
+}
+
+/// Participants of every contract that will be open once the response is applied
+fn resulting_contracts(response: &GmResponse, state: &GameState) -> BTreeMap<String, Vec<String>> {
+    let mut contracts: BTreeMap<String, Vec<String>> = state
+        .contracts
+        .values()
+        .map(|c| (c.id.clone(), c.participants.clone()))
+        .collect();
+
+    for update in &response.contracts {
+        match update.action.as_str() {
+            // Updates keep the participants the contract was created with
+            "create" => {
+                contracts.insert(update.id.clone(), update.participants.clone());
+            }
+            "end" => {
+                contracts.remove(&update.id);
+            }
+            _ => {}
+        }
+    }
+
+    contracts
+}
+
+/// Where every NPC will be once the response's state changes are applied
+fn resulting_locations<'a>(response: &'a GmResponse, state: &'a GameState) -> HashMap<&'a str, &'a str> {
+    let mut locations: HashMap<&str, &str> = state
+        .npcs
+        .iter()
+        .map(|(name, npc)| (name.as_str(), npc.location.as_str()))
+        .collect();
+
+    for change in &response.state_changes {
+        if let Some(location) = locations.get_mut(change.npc.as_str()) {
+            *location = change.location.as_str();
+        }
+    }
+
+    locations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::WorldClock;
+    use crate::partition::{merge_responses, Partition};
+    use crate::types::{Contract, ContractUpdate, Npc, StateChange};
+
+    /// Alice and Bob talking in the tavern, Carol there too and Dave on the square
+    fn state() -> GameState {
+        let mut state = GameState {
+            npcs: HashMap::new(),
+            contracts: HashMap::new(),
+            turn: 0,
+            clock: WorldClock::default(),
+        };
+        for (name, location) in [("alice", "tavern"), ("bob", "tavern"), ("carol", "tavern"), ("dave", "square")] {
+            state.npcs.insert(name.to_string(), Npc::new(name, location, "idling"));
+        }
+        state.contracts.insert("talk".to_string(), Contract {
+            id: "talk".to_string(),
+            participants: vec!["alice".to_string(), "bob".to_string()],
+            transcript_file: "talk.jsonl".to_string(),
+        });
+        state
+    }
+
+    fn world() -> World {
+        World::new()
+            .with_exit("tavern", "square", 1)
+            .with_exit("square", "market", 2)
+            .with_location("island", "Nobody knows the way")
+    }
+
+    fn intent(npc: &str) -> Intent {
+        Intent {
+            npc: npc.to_string(),
+            thought: String::new(),
+            action: "looks around".to_string(),
+            dialogue: None,
+        }
+    }
+
+    fn change(npc: &str, location: &str) -> StateChange {
+        StateChange {
+            npc: npc.to_string(),
+            location: location.to_string(),
+            activity: "walking".to_string(),
+        }
+    }
+
+    fn contract(id: &str, action: &str, participants: &[&str]) -> ContractUpdate {
+        ContractUpdate {
+            id: id.to_string(),
+            participants: participants.iter().map(|p| p.to_string()).collect(),
+            action: action.to_string(),
+            transcript_entry: None,
+        }
+    }
+
+    fn gm_response(state_changes: Vec<StateChange>, contracts: Vec<ContractUpdate>, prompted: &[&str]) -> GmResponse {
+        GmResponse {
+            reality: "Things happen.".to_string(),
+            state_changes,
+            contracts,
+            next_prompts: prompted.iter().map(|npc| (npc.to_string(), "What now?".to_string())).collect(),
+            local_realities: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn valid_response_has_no_diagnostics() {
+        let response = gm_response(
+            vec![change("carol", "tavern")],
+            vec![contract("talk", "update", &["alice", "bob"]), contract("cards", "create", &["carol"])],
+            &["alice", "carol"],
+        );
+        let diagnostics = validate_gm_response(&response, &state(), &[intent("alice"), intent("carol")]);
+        assert_eq!(diagnostics, []);
+    }
+
+    #[test]
+    fn reports_unknown_npc_in_state_change() {
+        let response = gm_response(vec![change("zed", "tavern")], vec![], &[]);
+        assert_eq!(
+            validate_gm_response(&response, &state(), &[]),
+            [Diagnostic::UnknownNpcInStateChange { npc: "zed".to_string() }]
+        );
+    }
+
+    #[test]
+    fn reports_unknown_npc_in_contract() {
+        let response = gm_response(vec![], vec![contract("cards", "create", &["carol", "zed"])], &[]);
+        assert_eq!(
+            validate_gm_response(&response, &state(), &[]),
+            [Diagnostic::UnknownNpcInContract { contract: "cards".to_string(), npc: "zed".to_string() }]
+        );
+    }
+
+    #[test]
+    fn reports_unknown_npc_in_next_prompts() {
+        let response = gm_response(vec![], vec![], &["zed"]);
+        assert_eq!(
+            validate_gm_response(&response, &state(), &[]),
+            [Diagnostic::UnknownNpcInNextPrompts { npc: "zed".to_string() }]
+        );
+    }
+
+    #[test]
+    fn reports_unknown_contract() {
+        let contracts = vec![contract("cards", "update", &["carol"]), contract("dice", "end", &[])];
+        let response = gm_response(vec![], contracts, &[]);
+        assert_eq!(
+            validate_gm_response(&response, &state(), &[]),
+            [
+                Diagnostic::UnknownContract { contract: "cards".to_string(), action: "update".to_string() },
+                Diagnostic::UnknownContract { contract: "dice".to_string(), action: "end".to_string() },
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_unknown_contract_action() {
+        let response = gm_response(vec![], vec![contract("talk", "pause", &["alice", "bob"])], &[]);
+        assert_eq!(
+            validate_gm_response(&response, &state(), &[]),
+            [Diagnostic::UnknownContractAction { contract: "talk".to_string(), action: "pause".to_string() }]
+        );
+    }
+
+    #[test]
+    fn reports_duplicate_contract_id() {
+        let response = gm_response(
+            vec![],
+            vec![
+                contract("talk", "create", &["alice", "bob"]),
+                contract("cards", "create", &["carol"]),
+                contract("cards", "create", &["carol"]),
+            ],
+            &[],
+        );
+        assert_eq!(
+            validate_gm_response(&response, &state(), &[]),
+            [
+                Diagnostic::DuplicateContractId { contract: "talk".to_string() },
+                Diagnostic::DuplicateContractId { contract: "cards".to_string() },
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_npc_in_multiple_contracts() {
+        let response = gm_response(vec![], vec![contract("cards", "create", &["bob", "carol"])], &[]);
+        assert_eq!(
+            validate_gm_response(&response, &state(), &[]),
+            [Diagnostic::NpcInMultipleContracts {
+                npc: "bob".to_string(),
+                contracts: vec!["cards".to_string(), "talk".to_string()],
+            }]
+        );
+
+        // Ending the old contract frees its participants
+        let contracts = vec![contract("talk", "end", &[]), contract("cards", "create", &["bob", "carol"])];
+        let response = gm_response(vec![], contracts, &[]);
+        assert_eq!(validate_gm_response(&response, &state(), &[]), []);
+    }
+
+    #[test]
+    fn reports_participants_not_colocated() {
+        let response = gm_response(vec![change("bob", "square")], vec![], &[]);
+        let diagnostics = validate_gm_response(&response, &state(), &[]);
+        let locations = BTreeMap::from([
+            ("alice".to_string(), "tavern".to_string()),
+            ("bob".to_string(), "square".to_string()),
+        ]);
+        assert_eq!(diagnostics, [Diagnostic::ParticipantsNotColocated { contract: "talk".to_string(), locations }]);
+        assert!(!diagnostics[0].is_fixable());
+    }
+
+    #[test]
+    fn reports_missing_next_prompt() {
+        let response = gm_response(vec![], vec![], &["alice"]);
+        let intents = [intent("alice"), intent("bob"), intent("zed")];
+        assert_eq!(
+            validate_gm_response(&response, &state(), &intents),
+            [Diagnostic::MissingNextPrompt { npc: "bob".to_string() }]
+        );
+    }
+
+    #[test]
+    fn reports_conflicts_between_partitions() {
+        let partitions = [
+            Partition { npcs: ["alice", "bob"].map(String::from).into(), ..Default::default() },
+            Partition { npcs: ["dave"].map(String::from).into(), ..Default::default() },
+        ];
+        let first = gm_response(
+            vec![change("dave", "tavern")],
+            vec![contract("talk", "update", &["alice", "bob"])],
+            &["dave"],
+        );
+        let second = gm_response(
+            vec![change("dave", "market")],
+            vec![contract("talk", "end", &["alice", "bob"])],
+            &["dave"],
+        );
+
+        let (merged, diagnostics) = merge_responses(&partitions, vec![first, second]);
+        assert_eq!(
+            diagnostics,
+            [
+                Diagnostic::ConflictingStateChanges { npc: "dave".to_string() },
+                Diagnostic::ConflictingContracts { contract: "talk".to_string() },
+                Diagnostic::ConflictingNextPrompts { npc: "dave".to_string() },
+            ]
+        );
+        // Each clash goes to the partition the NPC or contract belongs to
+        assert_eq!(merged.state_changes[0].location, "market");
+        assert_eq!(merged.contracts[0].action, "update");
+    }
+
+    #[test]
+    fn auto_fix_leaves_valid_response_alone() {
+        let mut fixed = gm_response(vec![change("carol", "square")], vec![], &["carol"]);
+        assert_eq!(auto_fix(&mut fixed, &state(), &[intent("carol")]), []);
+        assert_eq!(fixed.state_changes.len(), 1);
+        assert_eq!(fixed.next_prompts.len(), 1);
+    }
+
+    #[test]
+    fn auto_fix_drops_unknown_npcs() {
+        let mut fixed = gm_response(
+            vec![change("zed", "tavern"), change("carol", "tavern")],
+            vec![contract("cards", "create", &["carol", "zed"])],
+            &["zed", "carol"],
+        );
+        let diagnostics = auto_fix(&mut fixed, &state(), &[]);
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(fixed.state_changes.len(), 1);
+        assert_eq!(fixed.state_changes[0].npc, "carol");
+        assert_eq!(fixed.contracts[0].participants, ["carol"]);
+        assert!(!fixed.next_prompts.contains_key("zed"));
+    }
+
+    #[test]
+    fn auto_fix_turns_update_of_unknown_contract_into_create() {
+        let mut fixed = gm_response(vec![], vec![contract("cards", "update", &["carol"])], &[]);
+        auto_fix(&mut fixed, &state(), &[]);
+        assert_eq!(fixed.contracts[0].action, "create");
+        assert_eq!(validate_gm_response(&fixed, &state(), &[]), []);
+    }
+
+    #[test]
+    fn auto_fix_turns_duplicate_create_into_update() {
+        let mut fixed = gm_response(
+            vec![],
+            vec![
+                contract("talk", "create", &["alice", "bob"]),
+                contract("cards", "create", &["carol"]),
+                contract("cards", "create", &["carol"]),
+            ],
+            &[],
+        );
+        auto_fix(&mut fixed, &state(), &[]);
+        let actions: Vec<_> = fixed.contracts.iter().map(|u| (u.id.as_str(), u.action.as_str())).collect();
+        assert_eq!(actions, [("talk", "update"), ("cards", "create"), ("cards", "update")]);
+        assert_eq!(validate_gm_response(&fixed, &state(), &[]), []);
+    }
+
+    #[test]
+    fn auto_fix_drops_unknown_ends_and_actions() {
+        let mut fixed = gm_response(
+            vec![],
+            vec![
+                contract("dice", "end", &[]),
+                contract("talk", "pause", &["alice", "bob"]),
+                contract("talk", "update", &["alice", "bob"]),
+            ],
+            &[],
+        );
+        auto_fix(&mut fixed, &state(), &[]);
+        let actions: Vec<_> = fixed.contracts.iter().map(|u| (u.id.as_str(), u.action.as_str())).collect();
+        assert_eq!(actions, [("talk", "update")]);
+    }
+
+    #[test]
+    fn auto_fix_drops_participants_already_in_another_contract() {
+        let mut fixed = gm_response(
+            vec![],
+            vec![contract("cards", "create", &["bob", "carol"]), contract("dice", "create", &["alice", "carol"])],
+            &[],
+        );
+        auto_fix(&mut fixed, &state(), &[]);
+        // Bob stays in "talk"; Carol is taken by "cards" first, leaving "dice" with nobody
+        assert_eq!(fixed.contracts.len(), 1);
+        assert_eq!(fixed.contracts[0].id, "cards");
+        assert_eq!(fixed.contracts[0].participants, ["carol"]);
+        assert_eq!(validate_gm_response(&fixed, &state(), &[]), []);
+
+        // Unless their contract ends in the same response
+        let contracts = vec![contract("talk", "end", &[]), contract("cards", "create", &["bob", "carol"])];
+        let mut fixed = gm_response(vec![], contracts, &[]);
+        auto_fix(&mut fixed, &state(), &[]);
+        assert_eq!(fixed.contracts[1].participants, ["bob", "carol"]);
+    }
+
+    #[test]
+    fn auto_fix_adds_missing_next_prompts() {
+        let mut fixed = gm_response(vec![], vec![], &["alice"]);
+        auto_fix(&mut fixed, &state(), &[intent("alice"), intent("bob"), intent("zed")]);
+        assert_eq!(fixed.next_prompts["alice"], "What now?");
+        assert_eq!(fixed.next_prompts["bob"], DEFAULT_NEXT_PROMPT);
+        assert!(!fixed.next_prompts.contains_key("zed"));
+    }
+
+    #[test]
+    fn auto_fix_only_reports_participants_apart() {
+        let mut fixed = gm_response(vec![change("bob", "square")], vec![], &[]);
+        let diagnostics = auto_fix(&mut fixed, &state(), &[]);
+        assert!(matches!(diagnostics[..], [Diagnostic::ParticipantsNotColocated { .. }]));
+        assert_eq!(fixed.state_changes[0].location, "square");
+    }
+
+    #[test]
+    fn locations_on_the_map_are_fine() {
+        // Market is two exits away, which the engine turns into travel
+        let response = gm_response(vec![change("alice", "tavern"), change("carol", "market")], vec![], &[]);
+        assert_eq!(validate_locations(&response, &state(), &world()), []);
+
+        // NPCs somewhere off the map may step onto it anywhere
+        let mut state = state();
+        state.npcs.get_mut("dave").unwrap().location = "limbo".to_string();
+        let response = gm_response(vec![change("dave", "island")], vec![], &[]);
+        assert_eq!(validate_locations(&response, &state, &world()), []);
+    }
+
+    #[test]
+    fn fix_locations_keeps_npcs_out_of_unknown_locations() {
+        let mut fixed = gm_response(vec![change("carol", "narnia"), change("dave", "market")], vec![], &[]);
+        let diagnostics = fix_locations(&mut fixed, &state(), &world());
+        assert_eq!(
+            diagnostics,
+            [Diagnostic::UnknownLocation { npc: "carol".to_string(), location: "narnia".to_string() }]
+        );
+        assert_eq!(fixed.state_changes[0].location, "tavern");
+        assert_eq!(fixed.state_changes[0].activity, "walking");
+        assert_eq!(fixed.state_changes[1].location, "market");
+    }
+
+    #[test]
+    fn fix_locations_keeps_npcs_out_of_unreachable_locations() {
+        let mut fixed = gm_response(vec![change("dave", "island")], vec![], &[]);
+        let diagnostics = fix_locations(&mut fixed, &state(), &world());
+        assert_eq!(
+            diagnostics,
+            [Diagnostic::UnreachableLocation {
+                npc: "dave".to_string(),
+                from: "square".to_string(),
+                to: "island".to_string(),
+            }]
+        );
+        assert_eq!(fixed.state_changes[0].location, "square");
+    }
+}
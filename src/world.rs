@@ -0,0 +1,262 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
+use std::path::Path;
+
+use crate::error::{Error, IoResultExt, Result};
+use crate::types::{Leg, Npc, Travel};
+
+/// The places NPCs can be and how they connect, loaded from `data/world.json`
+///
+/// Exits work both ways: listing `"square": 2` under the tavern also lets NPCs walk from the
+/// square to the tavern in two turns, unless the square lists its own time.
+///
+/// ```json
+/// {
+///   "locations": {
+///     "tavern": { "description": "A smoky room with a long oak bar", "exits": { "square": 1 } },
+///     "square": { "description": "The cobbled heart of the village", "exits": { "forest": 3 } },
+///     "forest": { "description": "Dense pines and the smell of rain" }
+///   }
+/// }
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct World {
+    pub locations: BTreeMap<String, Location>,
+}
+
+/// A named place in the world
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Location {
+    pub description: String,
+    /// Neighbouring locations and the number of turns it takes to get to each
+    pub exits: BTreeMap<String, u32>,
+}
+
+impl World {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a location, keeping its exits if it already exists
+    pub fn with_location(mut self, name: impl Into<String>, description: impl Into<String>) -> Self {
+        self.locations.entry(name.into()).or_default().description = description.into();
+        self
+    }
+
+    /// Connect two locations both ways, adding either if missing
+    pub fn with_exit(mut self, from: impl Into<String>, to: impl Into<String>, turns: u32) -> Self {
+        let (from, to) = (from.into(), to.into());
+        self.locations.entry(from.clone()).or_default().exits.insert(to.clone(), turns);
+        self.locations.entry(to).or_default().exits.insert(from, turns);
+        self
+    }
+
+    /// Load `world.json` from a data directory, or None if there isn't one
+    pub fn load(data_path: impl AsRef<Path>) -> Result<Option<Self>> {
+        let path = data_path.as_ref().join("world.json");
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = std::fs::read_to_string(&path).at(&path)?;
+        let world: World = serde_json::from_str(&content)
+            .map_err(|e| Error::invalid(format!("Invalid world file {:?}: {}", path, e)))?;
+        let world = world
+            .validated()
+            .map_err(|e| Error::invalid(format!("Invalid world file {:?}: {}", path, e)))?;
+
+        log::info!("Loaded world with {} locations", world.locations.len());
+        Ok(Some(world))
+    }
+
+    /// Check every exit leads somewhere and add the missing reverse exits
+    pub fn validated(mut self) -> std::result::Result<Self, String> {
+        let mut reverse = Vec::new();
+        for (name, location) in &self.locations {
+            for (to, turns) in &location.exits {
+                if to == name {
+                    return Err(format!("location '{}' has an exit to itself", name));
+                }
+                if *turns == 0 {
+                    return Err(format!("exit from '{}' to '{}' must take at least one turn", name, to));
+                }
+                match self.locations.get(to) {
+                    None => return Err(format!("location '{}' has an exit to unknown location '{}'", name, to)),
+                    Some(other) if !other.exits.contains_key(name) => {
+                        reverse.push((to.clone(), name.clone(), *turns));
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+
+        for (from, to, turns) in reverse {
+            if let Some(location) = self.locations.get_mut(&from) {
+                location.exits.insert(to, turns);
+            }
+        }
+
+        Ok(self)
+    }
+
+    pub fn location(&self, name: &str) -> Option<&Location> {
+        self.locations.get(name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.locations.contains_key(name)
+    }
+
+    /// Turns needed to go straight from one location to a neighbouring one
+    pub fn travel_time(&self, from: &str, to: &str) -> Option<u32> {
+        self.locations.get(from)?.exits.get(to).copied()
+    }
+
+    /// The quickest way from one location to another, or None if it can't be reached
+    ///
+    /// Routes of equal length are chosen by location name so the same journey always takes
+    /// the same route. Going nowhere is an empty route. Exits to locations that aren't on the
+    /// map, which only a world built without `validated` can have, are never taken.
+    pub fn route(&self, from: &str, to: &str) -> Option<Vec<Leg>> {
+        if !self.contains(from) || !self.contains(to) {
+            return None;
+        }
+
+        let mut best: BTreeMap<&str, (u32, Option<&str>)> = BTreeMap::new();
+        let mut queue = BinaryHeap::new();
+        best.insert(from, (0, None));
+        queue.push(Reverse((0, from)));
+
+        while let Some(Reverse((cost, name))) = queue.pop() {
+            if name == to {
+                break;
+            }
+            if best.get(name).is_some_and(|(known, _)| *known < cost) {
+                continue;
+            }
+            for (next, turns) in &self.locations[name].exits {
+                if !self.contains(next) {
+                    continue;
+                }
+                let next_cost = cost + turns;
+                if best.get(next.as_str()).is_none_or(|(known, _)| next_cost < *known) {
+                    best.insert(next, (next_cost, Some(name)));
+                    queue.push(Reverse((next_cost, next.as_str())));
+                }
+            }
+        }
+
+        best.get(to)?;
+
+        let mut route = Vec::new();
+        let mut at = to;
+        while let Some((_, Some(previous))) = best.get(at) {
+            route.push(Leg {
+                to: at.to_string(),
+                turns: self.locations[*previous].exits[at],
+            });
+            at = previous;
+        }
+        route.reverse();
+        Some(route)
+    }
+
+    /// Set an NPC off towards a destination, spending this turn on the first leg
+    ///
+    /// Returns false, leaving the NPC alone, if the destination can't be reached from where
+    /// they are. NPCs next to the destination with a one-turn exit arrive straight away.
+    pub fn start_travel(&self, npc: &mut Npc, destination: &str) -> bool {
+        let Some(route) = self.route(&npc.location, destination) else {
+            return false;
+        };
+
+        npc.travel = (!route.is_empty()).then(|| Travel {
+            destination: destination.to_string(),
+            route,
+        });
+        npc.advance_travel();
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// tavern -1- square -2- forest -1- lake, plus a one-turn shortcut tavern -1- alley -1- lake
+    fn world() -> World {
+        World::new()
+            .with_exit("tavern", "square", 1)
+            .with_exit("square", "forest", 2)
+            .with_exit("forest", "lake", 1)
+            .with_exit("tavern", "alley", 1)
+            .with_exit("alley", "lake", 1)
+            .with_location("island", "Nobody knows the way")
+    }
+
+    fn stops(route: &[Leg]) -> Vec<(&str, u32)> {
+        route.iter().map(|leg| (leg.to.as_str(), leg.turns)).collect()
+    }
+
+    #[test]
+    fn route_takes_the_quickest_way() {
+        let world = world();
+        assert_eq!(stops(&world.route("tavern", "lake").unwrap()), [("alley", 1), ("lake", 1)]);
+        assert_eq!(stops(&world.route("square", "lake").unwrap()), [("tavern", 1), ("alley", 1), ("lake", 1)]);
+        assert_eq!(stops(&world.route("tavern", "forest").unwrap()), [("square", 1), ("forest", 2)]);
+        assert_eq!(world.route("tavern", "tavern").unwrap(), []);
+    }
+
+    #[test]
+    fn route_breaks_ties_by_location_name() {
+        // Both ways to the well take two turns; "bakery" sorts before "cellar"
+        let world = World::new()
+            .with_exit("home", "cellar", 1)
+            .with_exit("cellar", "well", 1)
+            .with_exit("home", "bakery", 1)
+            .with_exit("bakery", "well", 1);
+        for _ in 0..10 {
+            assert_eq!(stops(&world.route("home", "well").unwrap()), [("bakery", 1), ("well", 1)]);
+        }
+    }
+
+    #[test]
+    fn route_to_unreachable_or_unknown_location_is_none() {
+        let world = world();
+        assert!(world.route("tavern", "island").is_none());
+        assert!(world.route("island", "tavern").is_none());
+        assert!(world.route("tavern", "narnia").is_none());
+        assert!(world.route("narnia", "tavern").is_none());
+    }
+
+    #[test]
+    fn route_skips_dangling_exits() {
+        let mut world = World::new().with_exit("tavern", "square", 1);
+        world.locations.get_mut("tavern").unwrap().exits.insert("narnia".to_string(), 1);
+        world.locations.get_mut("square").unwrap().exits.insert("atlantis".to_string(), 1);
+
+        assert_eq!(stops(&world.route("tavern", "square").unwrap()), [("square", 1)]);
+        assert!(world.route("tavern", "narnia").is_none());
+        assert!(world.clone().validated().is_err());
+    }
+
+    #[test]
+    fn start_travel_spends_the_first_turn_on_the_way() {
+        let world = world();
+        let mut npc = Npc::new("alice", "tavern", "idling");
+
+        assert!(world.start_travel(&mut npc, "forest"));
+        assert_eq!(npc.location, "square");
+        assert_eq!(npc.travel.as_ref().unwrap().turns_left(), 2);
+
+        assert_eq!(npc.advance_travel(), None);
+        assert_eq!(npc.advance_travel().as_deref(), Some("forest"));
+        assert!(npc.travel.is_none());
+
+        assert!(!world.start_travel(&mut npc, "island"));
+        assert_eq!(npc.location, "forest");
+    }
+}
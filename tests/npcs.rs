@@ -0,0 +1,48 @@
+//! Adding NPCs to a running `NpcEngine`
+
+use social_npc::llm::MockLlmClient;
+use social_npc::{MemorySystem, NpcEngine, NpcStateFile};
+use tempfile::TempDir;
+
+#[test]
+fn init_npc_scaffolds_files_and_joins_the_game() {
+    let dir = TempDir::new().unwrap();
+    let engine = NpcEngine::new(dir.path(), MockLlmClient::new()).unwrap();
+    let start = NpcStateFile {
+        location: "forge".to_string(),
+        activity: "hammering a horseshoe".to_string(),
+        ..Default::default()
+    };
+
+    engine.init_npc("greta", Some(start)).unwrap();
+
+    let npc_dir = dir.path().join("npcs/greta");
+    let personality = std::fs::read_to_string(npc_dir.join("personality.md")).unwrap();
+    assert!(personality.starts_with("# greta"), "{}", personality);
+    let memories = std::fs::read_to_string(npc_dir.join("initial_memories.json")).unwrap();
+    serde_json::from_str::<MemorySystem>(&memories).unwrap();
+    assert!(npc_dir.join("state.json").exists());
+
+    // No reload needed
+    let greta = engine.get_state().npcs["greta"].clone();
+    assert_eq!((greta.location.as_str(), greta.activity.as_str()), ("forge", "hammering a horseshoe"));
+}
+
+#[test]
+fn init_npc_refuses_existing_and_invalid_names() {
+    let dir = TempDir::new().unwrap();
+    let engine = NpcEngine::new(dir.path(), MockLlmClient::new()).unwrap();
+    engine.init_npc("greta", None).unwrap();
+    std::fs::write(dir.path().join("npcs/greta/personality.md"), "# Greta, edited by hand").unwrap();
+
+    assert!(engine.init_npc("greta", None).is_err());
+    for name in ["", "../greta", ".hidden", "a/b"] {
+        assert!(engine.init_npc(name, None).is_err(), "{:?}", name);
+    }
+    let blank = NpcStateFile { location: " ".to_string(), ..Default::default() };
+    assert!(engine.init_npc("hans", Some(blank)).is_err());
+    assert!(!dir.path().join("npcs/hans").exists());
+
+    let personality = std::fs::read_to_string(dir.path().join("npcs/greta/personality.md")).unwrap();
+    assert_eq!(personality, "# Greta, edited by hand");
+}
@@ -0,0 +1,479 @@
+//! Whole turns through `NpcEngine`, scripted with `MockLlmClient`
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use social_npc::llm::{LlmClient, LlmRole, MockLlmClient, MockResponse};
+use social_npc::{
+    DefaultRelevanceGate, EngineEvent, Error, Intent, NpcEngine, NpcStateFile, RetryPolicy, RuleBasedResolver,
+    ScheduleEntry, TurnPhase, World,
+};
+use tempfile::TempDir;
+
+const MEMORY: &str = r#"{"immediate_self_context": "Busy morning at the tavern.", "new_self_memory": null, "relationship_updates": {}}"#;
+
+const GM: &str = r#"{
+    "reality": "Alice greets Bob, who pours her an ale.",
+    "state_changes": [
+        {"npc": "alice", "location": "tavern", "activity": "drinking an ale"},
+        {"npc": "bob", "location": "tavern", "activity": "pouring drinks"}
+    ],
+    "contracts": [],
+    "next_prompts": {
+        "alice": "Bob hands you an ale. What do you do?",
+        "bob": "Alice sips her ale. What do you do?"
+    }
+}"#;
+
+fn intent(npc: &str, action: &str, dialogue: Option<&str>) -> String {
+    serde_json::json!({
+        "npc": npc,
+        "thought": "...",
+        "action": action,
+        "dialogue": dialogue,
+    })
+    .to_string()
+}
+
+/// An engine over a fresh data directory with alice and bob in the tavern
+fn engine(mock: impl LlmClient + 'static) -> (NpcEngine, TempDir) {
+    let dir = TempDir::new().unwrap();
+    let mut engine = NpcEngine::new(dir.path(), mock)
+        .unwrap()
+        .with_retry_policy(RetryPolicy::none());
+    for name in ["alice", "bob"] {
+        engine.init_npc(name, None).unwrap();
+    }
+    engine.load_npcs().unwrap();
+    engine.set_npc_state("alice", "tavern", "sitting at the bar").unwrap();
+    engine.set_npc_state("bob", "tavern", "cleaning glasses").unwrap();
+    (engine, dir)
+}
+
+#[tokio::test]
+async fn full_turn_applies_the_gm_response() {
+    let mock = MockLlmClient::new()
+        .on_npc_role("alice", LlmRole::Intent, intent("alice", "greets Bob", Some("Morning, Bob!")))
+        .on_npc_role("bob", LlmRole::Intent, intent("bob", "pours an ale", None))
+        .on_role(LlmRole::Gm, GM)
+        .on_role(LlmRole::MemoryUpdate, MEMORY);
+    let (engine, _dir) = engine(mock);
+
+    let report = engine.execute_turn().await.unwrap();
+
+    assert!(report.is_clean(), "{:?}", report);
+    assert_eq!(report.turn, 1);
+    let actions: Vec<_> = report.intents.iter().map(|i| (i.npc.as_str(), i.action.as_str())).collect();
+    assert_eq!(actions, [("alice", "greets Bob"), ("bob", "pours an ale")]);
+    assert_eq!(report.reality.reality, "Alice greets Bob, who pours her an ale.");
+
+    let state = engine.get_state();
+    assert_eq!(state.turn, 1);
+    assert_eq!(state.npcs["alice"].activity, "drinking an ale");
+    assert_eq!(state.npcs["bob"].activity, "pouring drinks");
+    assert_eq!(state.npcs["alice"].next_prompt.as_deref(), Some("Bob hands you an ale. What do you do?"));
+}
+
+#[tokio::test]
+async fn failed_intent_drops_only_that_npc() {
+    let mock = MockLlmClient::new()
+        .on_npc_role("alice", LlmRole::Intent, "I'd rather not answer in JSON today.")
+        .on_npc_role("bob", LlmRole::Intent, intent("bob", "pours an ale", None))
+        .on_role(LlmRole::Gm, GM)
+        .on_role(LlmRole::MemoryUpdate, MEMORY);
+    let (engine, _dir) = engine(mock);
+
+    let report = engine.execute_turn().await.unwrap();
+
+    assert_eq!(report.errors.len(), 1);
+    assert_eq!(report.errors[0].npc, "alice");
+    assert_eq!(report.errors[0].phase, TurnPhase::Intents);
+    let acting: Vec<_> = report.intents.iter().map(|i| i.npc.as_str()).collect();
+    assert_eq!(acting, ["bob"]);
+    assert!(!report.is_clean());
+}
+
+#[tokio::test]
+async fn timed_out_intent_drops_only_that_npc() {
+    let mock = MockLlmClient::new()
+        .on_npc_role("alice", LlmRole::Intent, MockResponse::Timeout(Duration::from_millis(50)))
+        .on_npc_role("bob", LlmRole::Intent, intent("bob", "pours an ale", None))
+        .on_role(LlmRole::Gm, GM)
+        .on_role(LlmRole::MemoryUpdate, MEMORY);
+    let (engine, _dir) = engine(mock);
+
+    let report = engine.execute_turn().await.unwrap();
+
+    assert_eq!(report.errors.len(), 1);
+    assert_eq!(report.errors[0].npc, "alice");
+    assert!(report.errors[0].error.contains("timed out"), "{}", report.errors[0].error);
+    let acting: Vec<_> = report.intents.iter().map(|i| i.npc.as_str()).collect();
+    assert_eq!(acting, ["bob"]);
+}
+
+#[tokio::test]
+async fn slow_intent_falls_back_to_the_current_activity() {
+    let mock = MockLlmClient::new()
+        .on_npc_role(
+            "alice",
+            LlmRole::Intent,
+            MockResponse::Delayed(Duration::from_secs(5), intent("alice", "greets Bob", None)),
+        )
+        .on_npc_role("bob", LlmRole::Intent, intent("bob", "pours an ale", None))
+        .on_role(LlmRole::Gm, GM)
+        .on_role(LlmRole::MemoryUpdate, MEMORY);
+    let (engine, _dir) = engine(mock);
+    let engine = engine.with_intent_deadline(Duration::from_millis(100));
+
+    let report = engine.execute_turn().await.unwrap();
+
+    assert_eq!(report.fallbacks, ["alice"]);
+    assert!(report.errors.is_empty());
+    let alice = report.intents.iter().find(|i| i.npc == "alice").unwrap();
+    assert_eq!(alice.action, "continues sitting at the bar");
+    assert!(report.intents.iter().any(|i| i.npc == "bob" && i.action == "pours an ale"));
+}
+
+#[tokio::test]
+async fn unparseable_gm_response_fails_the_turn() {
+    let mock = MockLlmClient::new()
+        .on_npc_role("alice", LlmRole::Intent, intent("alice", "greets Bob", None))
+        .on_npc_role("bob", LlmRole::Intent, intent("bob", "pours an ale", None))
+        .on_role(LlmRole::Gm, "The tavern falls silent and nothing is decided.")
+        .on_role(LlmRole::MemoryUpdate, MEMORY);
+    let (engine, _dir) = engine(mock);
+
+    let error = engine.execute_turn().await.unwrap_err();
+
+    assert!(error.is_parse_error(), "{:?}", error);
+    assert_eq!(error.raw_response(), Some("The tavern falls silent and nothing is decided."));
+    let state = engine.get_state();
+    assert_eq!(state.turn, 0);
+    assert_eq!(state.npcs["alice"].activity, "sitting at the bar");
+}
+
+#[tokio::test]
+async fn auto_snapshots_leave_state_files_alone() {
+    let mock = MockLlmClient::new()
+        .on_role(LlmRole::Intent, intent("alice", "waits", None))
+        .on_role(LlmRole::Gm, GM)
+        .on_role(LlmRole::MemoryUpdate, MEMORY);
+    let (engine, dir) = engine(mock);
+    let engine = engine.with_auto_snapshots();
+    let state_file = dir.path().join("npcs/alice/state.json");
+    std::fs::write(&state_file, r#"{"location": "tavern", "activity": "sitting at the bar"}"#).unwrap();
+
+    engine.execute_turn().await.unwrap();
+
+    assert!(dir.path().join("snapshots").read_dir().unwrap().next().is_some());
+    let written = std::fs::read_to_string(&state_file).unwrap();
+    assert_eq!(written, r#"{"location": "tavern", "activity": "sitting at the bar"}"#);
+
+    engine.save_snapshot(dir.path().join("manual.json")).unwrap();
+    let saved = std::fs::read_to_string(&state_file).unwrap();
+    assert!(saved.contains("drinking an ale"), "{}", saved);
+}
+
+#[tokio::test]
+async fn travel_takes_as_many_turns_as_the_route() {
+    let gm = r#"{
+        "reality": "Alice sets off for the forest.",
+        "state_changes": [{"npc": "alice", "location": "forest", "activity": "walking to the forest"}],
+        "contracts": [],
+        "next_prompts": {"alice": "The road goes on. What do you do?"}
+    }"#;
+    let mock = MockLlmClient::new()
+        .on_npc_role("alice", LlmRole::Intent, intent("alice", "walks to the forest", None))
+        .on_npc_role("bob", LlmRole::Intent, intent("bob", "cleans glasses", None))
+        .on_role(LlmRole::Gm, gm)
+        .on_role(LlmRole::MemoryUpdate, MEMORY);
+    let (engine, _dir) = engine(mock);
+    let world = World::new().with_exit("tavern", "square", 1).with_exit("square", "forest", 2);
+    let engine = engine.with_world(world);
+
+    // The first leg takes one turn, the second two
+    let mut seen = Vec::new();
+    for _ in 0..4 {
+        engine.execute_turn().await.unwrap();
+        let alice = engine.get_state().npcs["alice"].clone();
+        seen.push((alice.location, alice.travel.map(|travel| travel.turns_left())));
+    }
+
+    assert_eq!(
+        seen,
+        [
+            ("square".to_string(), Some(2)),
+            ("square".to_string(), Some(1)),
+            ("forest".to_string(), None),
+            ("forest".to_string(), None),
+        ]
+    );
+    assert_eq!(engine.get_state().npcs["bob"].location, "tavern");
+}
+
+#[tokio::test]
+async fn relevance_gate_ignores_changes_npcs_made_themselves() {
+    let mock = MockLlmClient::new()
+        .on_npc_role("alice", LlmRole::Intent, intent("alice", "greets Bob", None))
+        .on_npc_role("bob", LlmRole::Intent, intent("bob", "pours an ale", None))
+        .on_role(LlmRole::Gm, GM)
+        .on_role(LlmRole::MemoryUpdate, MEMORY);
+    let (engine, _dir) = engine(mock);
+    let engine = engine.with_relevance_gate(DefaultRelevanceGate::new().with_wake_chance(0.0));
+    let idle = |report: &social_npc::TurnReport| report.idle.iter().map(|i| i.npc.clone()).collect::<Vec<_>>();
+
+    // The GM changed both activities on the first turn, but that was their own doing
+    let first = engine.execute_turn().await.unwrap();
+    let second = engine.execute_turn().await.unwrap();
+    engine.set_npc_state("bob", "square", "sweeping").unwrap();
+    let third = engine.execute_turn().await.unwrap();
+
+    assert!(idle(&first).is_empty());
+    assert_eq!(idle(&second), ["alice", "bob"]);
+    assert!(idle(&third).is_empty(), "{:?}", third.idle);
+}
+
+#[tokio::test]
+async fn streaming_broadcasts_dialogue_by_speaker() {
+    let gm = r#"{
+        "reality": "Alice greets Bob, who answers.",
+        "state_changes": [],
+        "contracts": [{
+            "id": "morning",
+            "participants": ["alice", "bob"],
+            "action": "create",
+            "transcript_entry": {
+                "reality": "Alice greets Bob.",
+                "details": {"bob": {"action": "nods", "dialogue": "Morning."}}
+            }
+        }],
+        "next_prompts": {}
+    }"#;
+    let mock = MockLlmClient::new()
+        .on_npc_role("alice", LlmRole::Intent, intent("alice", "greets Bob", Some("Morning, Bob!")))
+        .on_npc_role("bob", LlmRole::Intent, intent("bob", "pours an ale", None))
+        .on_role(LlmRole::Gm, gm)
+        .on_role(LlmRole::MemoryUpdate, MEMORY);
+    let (engine, _dir) = engine(mock);
+    let engine = engine.with_streaming(true);
+    let mut events = engine.subscribe();
+
+    engine.execute_turn().await.unwrap();
+
+    let mut spoken = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        if let EngineEvent::DialogueChunk { context, speaker, text, done } = event {
+            spoken.push((context.role, speaker, text, done));
+        }
+    }
+    assert_eq!(
+        spoken,
+        [
+            (LlmRole::Intent, "alice".to_string(), "Morning, Bob!".to_string(), true),
+            (LlmRole::Gm, "bob".to_string(), "Morning.".to_string(), true),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn conversations_need_a_speaker_in_the_game() {
+    let reply = r#"{"thought": "...", "action": "looks up", "dialogue": "Evening."}"#;
+    let mock = Arc::new(MockLlmClient::new().on_role(LlmRole::Dialogue, reply).on_role(LlmRole::MemoryUpdate, MEMORY));
+    let dir = TempDir::new().unwrap();
+    let mut engine = NpcEngine::new(dir.path(), mock.clone()).unwrap().with_retry_policy(RetryPolicy::none());
+    engine.init_npc("alice", None).unwrap();
+    engine.load_npcs().unwrap();
+    engine.register_player("hero", "tavern", "standing at the door").unwrap();
+
+    let stranger = engine.converse("alice", "stranger", "Hello?").await.unwrap_err();
+    assert!(matches!(&stranger, Error::NpcNotFound { name } if name == "stranger"), "{:?}", stranger);
+    assert!(engine.converse("alice", "alice", "Hello?").await.is_err());
+    assert!(mock.received_for(LlmRole::Dialogue).is_empty());
+    assert!(engine.get_state().contracts.is_empty());
+
+    let reply = engine.converse("alice", "hero", "Hello?").await.unwrap();
+    assert_eq!(reply.dialogue.as_deref(), Some("Evening."));
+    let contracts: Vec<_> = engine.get_state().contracts.into_values().map(|c| c.participants).collect();
+    assert_eq!(contracts, [["hero", "alice"]]);
+}
+
+#[tokio::test]
+async fn events_follow_the_turn_phases() {
+    let mock = MockLlmClient::new()
+        .on_npc_role("alice", LlmRole::Intent, intent("alice", "greets Bob", Some("Morning, Bob!")))
+        .on_npc_role("bob", LlmRole::Intent, intent("bob", "pours an ale", None))
+        .on_role(LlmRole::Gm, GM)
+        .on_role(LlmRole::MemoryUpdate, MEMORY);
+    let (engine, _dir) = engine(mock);
+    let mut events = engine.subscribe();
+
+    engine.execute_turn().await.unwrap();
+
+    let mut kinds = Vec::new();
+    let mut llm_calls = 0;
+    while let Ok(event) = events.try_recv() {
+        match event {
+            EngineEvent::TurnStarted { turn } | EngineEvent::TurnFinished { turn, .. } => assert_eq!(turn, 1),
+            EngineEvent::LlmCallFinished { .. } => llm_calls += 1,
+            _ => {}
+        }
+        // LLM calls for different NPCs may overlap, so leave them out of the order
+        let kind = serde_json::to_value(&event).unwrap()["type"].as_str().unwrap().to_string();
+        if !kind.starts_with("llm_call") {
+            kinds.push(kind);
+        }
+    }
+
+    assert_eq!(
+        kinds,
+        [
+            "turn_started",
+            "intent_collected",
+            "intent_collected",
+            "gm_resolved",
+            "npc_activity_changed",
+            "npc_activity_changed",
+            "memory_updated",
+            "memory_updated",
+            "turn_finished",
+        ]
+    );
+    assert_eq!(llm_calls, 5);
+}
+
+#[tokio::test]
+async fn players_act_through_submitted_intents() {
+    let gm = r#"{
+        "reality": "The hero orders a round and Bob pours it.",
+        "state_changes": [{"npc": "hero", "location": "tavern", "activity": "raising a mug"}],
+        "contracts": [],
+        "next_prompts": {"hero": "Bob slides three mugs over. What do you do?"}
+    }"#;
+    let mock = Arc::new(
+        MockLlmClient::new()
+            .on_npc_role("alice", LlmRole::Intent, intent("alice", "cheers", None))
+            .on_npc_role("bob", LlmRole::Intent, intent("bob", "pours a round", None))
+            .on_role(LlmRole::Gm, gm)
+            .on_role(LlmRole::MemoryUpdate, MEMORY),
+    );
+    let (engine, _dir) = engine(mock.clone());
+    engine.register_player("hero", "tavern", "leaning on the bar").unwrap();
+    let hero = Intent {
+        npc: "hero".to_string(),
+        thought: String::new(),
+        action: "orders a round".to_string(),
+        dialogue: Some("Drinks for everyone!".to_string()),
+    };
+    let not_a_player = Intent { npc: "alice".to_string(), ..hero.clone() };
+    assert!(engine.submit_player_intent(not_a_player).is_err());
+    engine.submit_player_intent(hero).unwrap();
+
+    let report = engine.execute_turn().await.unwrap();
+
+    assert!(report.intents.iter().any(|i| i.npc == "hero" && i.action == "orders a round"));
+    let asked = |role| -> Vec<String> {
+        mock.received_for(role).into_iter().filter_map(|q| q.context.and_then(|c| c.npc)).collect()
+    };
+    assert!(!asked(LlmRole::Intent).contains(&"hero".to_string()));
+    assert!(!asked(LlmRole::MemoryUpdate).contains(&"hero".to_string()));
+    assert_eq!(engine.get_state().npcs["hero"].activity, "raising a mug");
+    assert_eq!(engine.player_prompt("hero").as_deref(), Some("Bob slides three mugs over. What do you do?"));
+
+    // Nothing queued, so the player sits the next turn out
+    let report = engine.execute_turn().await.unwrap();
+    assert!(report.intents.iter().all(|i| i.npc != "hero"));
+}
+
+#[tokio::test]
+async fn npcs_alone_follow_their_schedule_without_llm_calls() {
+    let mock = Arc::new(
+        MockLlmClient::new()
+            .on_npc_role("alice", LlmRole::Intent, intent("alice", "greets Bob", None))
+            .on_npc_role("bob", LlmRole::Intent, intent("bob", "pours an ale", None))
+            .on_role(LlmRole::Gm, GM)
+            .on_role(LlmRole::MemoryUpdate, MEMORY),
+    );
+    let (engine, _dir) = engine(mock.clone());
+    let engine = engine.with_auto_routines(true);
+    let block = |start_hour, end_hour, location: &str, activity: &str| ScheduleEntry {
+        start_hour,
+        end_hour,
+        location: location.to_string(),
+        activity: activity.to_string(),
+    };
+    let start = NpcStateFile {
+        location: "home".to_string(),
+        activity: "sleeping".to_string(),
+        schedule: vec![block(8, 9, "home", "eating breakfast"), block(9, 18, "forge", "hammering")],
+        ..Default::default()
+    };
+    engine.init_npc("greta", Some(start)).unwrap();
+
+    // Turns start at 08:00 and take half an hour each
+    let mut seen = Vec::new();
+    for _ in 0..3 {
+        let report = engine.execute_turn().await.unwrap();
+        let greta = engine.get_state().npcs["greta"].clone();
+        seen.push((report.routines, greta.location, greta.activity));
+    }
+
+    let greta = || vec!["greta".to_string()];
+    assert_eq!(
+        seen,
+        [
+            (greta(), "home".to_string(), "eating breakfast".to_string()),
+            (greta(), "home".to_string(), "eating breakfast".to_string()),
+            (greta(), "forge".to_string(), "hammering".to_string()),
+        ]
+    );
+    let asked: Vec<_> = mock.received().into_iter().filter_map(|q| q.context.and_then(|c| c.npc)).collect();
+    assert!(!asked.contains(&"greta".to_string()), "{:?}", asked);
+    // Alice and Bob have company, so they are asked as usual
+    assert_eq!(mock.received_for(LlmRole::Intent).len(), 6);
+}
+
+#[tokio::test]
+async fn failed_gm_falls_back_to_the_rules() {
+    let mock = MockLlmClient::new()
+        .on_npc_role("alice", LlmRole::Intent, intent("alice", "greets Bob", Some("Morning, Bob!")))
+        .on_npc_role("bob", LlmRole::Intent, intent("bob", "pours an ale", None))
+        .on_role(LlmRole::Gm, "The tavern falls silent and nothing is decided.")
+        .on_role(LlmRole::MemoryUpdate, MEMORY);
+    let (engine, _dir) = engine(mock);
+    let engine = engine.with_gm_fallback(RuleBasedResolver);
+    let mut events = engine.subscribe();
+
+    let report = engine.execute_turn().await.unwrap();
+
+    let mut fallbacks = 0;
+    while let Ok(event) = events.try_recv() {
+        if let EngineEvent::GmFallback { error } = event {
+            assert!(error.contains("no JSON"), "{}", error);
+            fallbacks += 1;
+        }
+    }
+    assert_eq!(fallbacks, 1);
+    assert!(report.reality.reality.contains("alice greets Bob."), "{}", report.reality.reality);
+    let state = engine.get_state();
+    assert_eq!(state.npcs["alice"].activity, "greets Bob");
+    assert_eq!(state.npcs["bob"].activity, "pours an ale");
+    assert_eq!(state.contracts.keys().collect::<Vec<_>>(), ["conv_alice_bob_day1_0800"]);
+}
+
+#[tokio::test]
+async fn rule_based_resolver_needs_no_gm_calls() {
+    let mock = Arc::new(
+        MockLlmClient::new()
+            .on_npc_role("alice", LlmRole::Intent, intent("alice", "sips her ale", None))
+            .on_npc_role("bob", LlmRole::Intent, intent("bob", "pours an ale", None))
+            .on_role(LlmRole::MemoryUpdate, MEMORY),
+    );
+    let (engine, _dir) = engine(mock.clone());
+    let engine = engine.with_gm_resolver(RuleBasedResolver);
+
+    let report = engine.execute_turn().await.unwrap();
+
+    assert!(report.is_clean(), "{:?}", report);
+    assert!(mock.received_for(LlmRole::Gm).is_empty());
+    assert_eq!(engine.get_state().npcs["alice"].activity, "sips her ale");
+}